@@ -2,6 +2,7 @@
 
 use euclid::default::{Point2D, Vector2D};
 use simulator::bodies::*;
+use std::f64::consts::PI;
 
 /// Use this struct to construct a solar system easily
 // 'a says: all the references this uses will last for as long as the SSB does
@@ -13,6 +14,12 @@ pub struct SolarSystemBuilder {
     used_up: bool,
 }
 
+impl Default for SolarSystemBuilder {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
 impl SolarSystemBuilder {
     /// Make a new empty Builder.
     pub fn new() -> Self {
@@ -117,10 +124,10 @@ impl SolarSystemBuilder {
 
                 out.extend((0..count).map(|num| {
                     let mass = rand.gen_range(min_mass, max_mass);
-                    let radius = (mass / DENSITY * 3.0 / (4.0 * 3.14159)).cbrt();
+                    let radius = (mass / DENSITY * 3.0 / (4.0 * PI)).cbrt();
                     // Do some math for a circular orbit
                     let total_mass = mass + parent_mass;
-                    let theta = rand.gen_range(0f64, 2.0 * 3.14159f64);
+                    let theta = rand.gen_range(0f64, 2.0 * PI);
                     let orbit = rand.gen_range(min_orbit, max_orbit);
                     let pos_x = theta.cos() * orbit;
                     let pos_y = theta.sin() * orbit;
@@ -138,6 +145,8 @@ impl SolarSystemBuilder {
                             outline: 0xeeddee,
                             name: format!("{}-{}", system_name, num),
                             immovable: false,
+                            test_particle: false,
+                            collision: None,
                         },
                         Kinemat::new(
                             Point2D::new(pos_x, pos_y) + parent_pos.to_vector(),
@@ -154,6 +163,7 @@ impl SolarSystemBuilder {
                 max_bodies,
                 seed,
                 clockwise,
+                test_particles,
             } => {
                 use rand::{rngs::SmallRng, Rng, SeedableRng};
                 use rand_distr::{Distribution, Normal};
@@ -207,12 +217,12 @@ impl SolarSystemBuilder {
                             (5320.0, 0xC9D2E4, 0x618CD6, 'M')
                         };
 
-                    let radius = (mass / density * 3.0 / (4.0 * 3.14159)).cbrt();
+                    let radius = (mass / density * 3.0 / (4.0 * PI)).cbrt();
                     let name = format!("{}-{:04}{}", system_name, asteroids.len(), id_char);
 
                     // Kinematic info
                     let system_mass = mass + parent_mass;
-                    let theta = rand.gen_range(0f64, 2.0 * 3.14159f64);
+                    let theta = rand.gen_range(0f64, 2.0 * PI);
                     let orbit = rand.gen_range(min_orbit, max_orbit);
                     let pos_x = theta.cos() * orbit;
                     let pos_y = theta.sin() * orbit;
@@ -229,6 +239,8 @@ impl SolarSystemBuilder {
                             outline,
                             name,
                             immovable: false,
+                            test_particle: test_particles,
+                            collision: None,
                         },
                         Kinemat::new(Point2D::new(pos_x, pos_y), Vector2D::new(vel_x, vel_y)),
                     ))
@@ -261,6 +273,8 @@ pub enum SolarSystemBuilderEntry {
     /// AsteroidsBuilder is like a MoonBuilder but builds Asteroids instead.
     /// Give it the total mass of the asteroids.
     /// This way you don't end up with an asteroid belt heavier than the sun...
+    /// With `test_particles`, the asteroids are test particles, so they don't pull on anything
+    /// and their masses only decide how big they are. Much faster for huge belts.
     AsteroidsBuilder {
         total_mass: f64,
         min_orbit: f64,
@@ -269,6 +283,7 @@ pub enum SolarSystemBuilderEntry {
         max_bodies: Option<usize>,
         seed: u64,
         clockwise: bool,
+        test_particles: bool,
     },
 }
 
@@ -292,6 +307,7 @@ impl SolarSystemBuilderEntry {
 
     /// Add another SolarSystemBuilderEntry as a child of this one.
     /// Returns itself so you can keep chaining it.
+    #[allow(clippy::should_implement_trait)]
     pub fn add(mut self, child: Self) -> Self {
         match &mut self {
             // The `ref` keyword is actually black magic
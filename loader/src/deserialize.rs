@@ -1,6 +1,9 @@
 //! Lets you load a SolarSystem from a file.
 
-use serde::Deserialize;
+use serde::de::value::{MapAccessDeserializer, SeqAccessDeserializer};
+use serde::de::{MapAccess, SeqAccess, Visitor};
+use serde::{Deserialize, Deserializer};
+use std::fmt;
 
 /// A Vector2D or Point2D.
 #[derive(Deserialize)]
@@ -44,6 +47,8 @@ enum Entry {
         seed: u64,
         #[serde(default)]
         clockwise: bool,
+        #[serde(default)]
+        test_particles: bool,
     },
 }
 
@@ -65,6 +70,10 @@ enum Body {
         outline: u32,
         #[serde(default)]
         immovable: bool,
+        #[serde(default)]
+        test_particle: bool,
+        #[serde(default)]
+        collision: Option<CollisionModel>,
     },
 }
 
@@ -75,32 +84,252 @@ struct Kinemat {
 }
 
 /// Serde needs you to define the thing to use it on...
+enum RawSolarSystem {
+    /// Just the entries, with the default settings
+    Bare(Vec<Entry>),
+    /// The entries along with settings for the simulation
+    WithConfig(WithConfig),
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct WithConfig {
+    #[serde(default)]
+    config: Box<Config>,
+    bodies: Vec<Entry>,
+}
+
+// Not `untagged`: that throws away whatever actually went wrong and just says nothing matched.
+// A list is always the bare form and an object always has settings, so pick by that and keep the real error.
+impl<'de> Deserialize<'de> for RawSolarSystem {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        struct RawVisitor;
+
+        impl<'de> Visitor<'de> for RawVisitor {
+            type Value = RawSolarSystem;
+
+            fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+                formatter.write_str("a list of bodies, or an object with `bodies` and `config`")
+            }
+
+            fn visit_seq<A: SeqAccess<'de>>(self, seq: A) -> Result<Self::Value, A::Error> {
+                Vec::deserialize(SeqAccessDeserializer::new(seq)).map(RawSolarSystem::Bare)
+            }
+
+            fn visit_map<A: MapAccess<'de>>(self, map: A) -> Result<Self::Value, A::Error> {
+                WithConfig::deserialize(MapAccessDeserializer::new(map))
+                    .map(RawSolarSystem::WithConfig)
+            }
+        }
+
+        deserializer.deserialize_any(RawVisitor)
+    }
+}
+
+/// Settings for the simulation. Anything left out uses the default.
+#[derive(Deserialize, Default)]
+#[serde(deny_unknown_fields)]
+struct Config {
+    grav_constant: Option<f64>,
+    min_pull_mass: Option<f64>,
+    max_pull_distance: Option<f64>,
+    softening: Option<Softening>,
+    save_every: Option<usize>,
+    save_every_seconds: Option<f64>,
+    save_count: Option<usize>,
+    memory_budget: Option<usize>,
+    keyframe_every: Option<usize>,
+    collisions: Option<CollisionModel>,
+    fragmentation: Option<Fragmentation>,
+    epoch: Option<Epoch>,
+    on_rails_below: Option<f64>,
+}
+
+/// Either a Julian date like `2451545.0`, or a string like `"J2000"` or `"2000-01-01 12:00:00"`
+#[derive(Deserialize)]
+#[serde(try_from = "RawEpoch")]
+struct Epoch(clock::Epoch);
+
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum RawEpoch {
+    JulianDate(f64),
+    Date(String),
+}
+
+impl TryFrom<RawEpoch> for Epoch {
+    type Error = clock::ParseEpochError;
+
+    fn try_from(raw: RawEpoch) -> Result<Self, Self::Error> {
+        match raw {
+            RawEpoch::JulianDate(julian_date) => {
+                Ok(Epoch(clock::Epoch::from_julian_date(julian_date)))
+            }
+            RawEpoch::Date(date) => date.parse().map(Epoch),
+        }
+    }
+}
+
+/// Breaking things apart in violent collisions. Anything left out uses the default.
+#[derive(Deserialize, Default)]
+#[serde(deny_unknown_fields)]
+struct Fragmentation {
+    threshold: Option<f64>,
+    max_fragments: Option<usize>,
+    power_law_index: Option<f64>,
+    min_fragment_mass: Option<f64>,
+    seed: Option<u64>,
+}
+
+impl From<Fragmentation> for config::Fragmentation {
+    fn from(raw: Fragmentation) -> Self {
+        let default = config::Fragmentation::default();
+        config::Fragmentation {
+            threshold: raw.threshold.unwrap_or(default.threshold),
+            max_fragments: raw.max_fragments.unwrap_or(default.max_fragments),
+            power_law_index: raw.power_law_index.unwrap_or(default.power_law_index),
+            min_fragment_mass: raw.min_fragment_mass.unwrap_or(default.min_fragment_mass),
+            seed: raw.seed.unwrap_or(default.seed),
+        }
+    }
+}
+
+/// Either a Plummer softening length like `1e6`, `"none"`, or `{ spline: { length: 3e6 } }`
 #[derive(Deserialize)]
-struct RawSolarSystem(Vec<Entry>);
+#[serde(untagged)]
+enum Softening {
+    Length(f64),
+    Kernel(SofteningKernel),
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "snake_case")]
+enum SofteningKernel {
+    None,
+    Plummer { length: f64 },
+    Spline { length: f64 },
+}
+
+impl From<Softening> for config::Softening {
+    fn from(raw: Softening) -> Self {
+        match raw {
+            // 0 always meant no softening
+            Softening::Length(0.0) => config::Softening::None,
+            Softening::Length(length) => config::Softening::Plummer { length },
+            Softening::Kernel(SofteningKernel::None) => config::Softening::None,
+            Softening::Kernel(SofteningKernel::Plummer { length }) => {
+                config::Softening::Plummer { length }
+            }
+            Softening::Kernel(SofteningKernel::Spline { length }) => {
+                config::Softening::Spline { length }
+            }
+        }
+    }
+}
+
+/// Either a plain string like `"merge"`, or `{ elastic: { restitution: 0.8 } }`
+#[derive(Deserialize)]
+#[serde(rename_all = "snake_case")]
+enum CollisionModel {
+    Merge,
+    Elastic { restitution: f64 },
+    PassThrough,
+    DestroySmaller,
+}
+
+impl From<CollisionModel> for config::CollisionModel {
+    fn from(raw: CollisionModel) -> Self {
+        match raw {
+            CollisionModel::Merge => config::CollisionModel::Merge,
+            CollisionModel::Elastic { restitution } => {
+                config::CollisionModel::Elastic { restitution }
+            }
+            CollisionModel::PassThrough => config::CollisionModel::PassThrough,
+            CollisionModel::DestroySmaller => config::CollisionModel::DestroySmaller,
+        }
+    }
+}
 
 use crate::builder::{SolarSystemBuilder, SolarSystemBuilderEntry as SSBE};
 use euclid::default::{Point2D, Vector2D};
 use json5;
 use simulator::bodies;
+use simulator::clock;
+use simulator::config::{self, SimulationConfig};
+use std::convert::TryFrom;
+
+/// Everything a scenario file says about a solar system.
+pub struct Scenario {
+    pub orbiters: Vec<bodies::Orbiter>,
+    pub config: SimulationConfig,
+}
 
 /// Loads a file and returns the ingredients for a solar system.
+/// Any settings in the file are ignored; use `load_scenario` to get them.
 pub fn load(contents: String) -> Result<Vec<bodies::Orbiter>, json5::Error> {
+    Ok(load_scenario(contents)?.orbiters)
+}
+
+/// Loads a file and returns the ingredients for a solar system,
+/// along with the settings to simulate it with.
+pub fn load_scenario(contents: String) -> Result<Scenario, json5::Error> {
     let contents = &*contents;
     let raw: RawSolarSystem = json5::from_str(contents)?;
+    let (raw_config, entries) = match raw {
+        RawSolarSystem::Bare(entries) => (Config::default(), entries),
+        RawSolarSystem::WithConfig(WithConfig { config, bodies }) => (*config, bodies),
+    };
     let builder = &mut SolarSystemBuilder::new();
 
-    for root in raw.0 {
+    for root in entries {
         builder.add(do_one_level(root));
     }
 
-    Ok(builder.construct())
+    Ok(Scenario {
+        orbiters: builder.construct(),
+        config: convert_config(raw_config),
+    })
+}
+
+/// Convert from serde to real, filling in the defaults
+fn convert_config(raw: Config) -> SimulationConfig {
+    let mut builder = SimulationConfig::builder();
+    if let Some(grav_constant) = raw.grav_constant {
+        builder.grav_constant(grav_constant);
+    }
+    if let Some(min_pull_mass) = raw.min_pull_mass {
+        builder.min_pull_mass(min_pull_mass);
+    }
+    if let Some(max_pull_distance) = raw.max_pull_distance {
+        builder.max_pull_distance(max_pull_distance);
+    }
+    if let Some(softening) = raw.softening {
+        builder.softening(softening.into());
+    }
+    let default_history = config::HistoryPolicy::default();
+    builder.history(config::HistoryPolicy {
+        save_every: raw.save_every.unwrap_or(default_history.save_every),
+        save_every_seconds: raw
+            .save_every_seconds
+            .unwrap_or(default_history.save_every_seconds),
+        save_count: raw.save_count.unwrap_or(default_history.save_count),
+        memory_budget: raw.memory_budget.unwrap_or(default_history.memory_budget),
+        keyframe_every: raw.keyframe_every.unwrap_or(default_history.keyframe_every),
+    });
+    if let Some(collisions) = raw.collisions {
+        builder.collisions(collisions.into());
+    }
+    builder.fragmentation(raw.fragmentation.map(Into::into));
+    builder.epoch(raw.epoch.map(|epoch| epoch.0));
+    builder.on_rails_below(raw.on_rails_below);
+    builder.build()
 }
 
 /// Helper function to DFS convert from serde to real
 fn do_one_level(entry: Entry) -> SSBE {
     match entry {
         Entry::Locus { pos, mut children } => SSBE::new_locus(Point2D::new(pos.0, pos.1))
-            .add_bulk(children.drain(0..).map(|kid| do_one_level(kid))),
+            .add_bulk(children.drain(0..).map(do_one_level)),
         Entry::Orbiter {
             body,
             kinemat,
@@ -115,6 +344,8 @@ fn do_one_level(entry: Entry) -> SSBE {
                     color,
                     outline,
                     immovable,
+                    test_particle,
+                    collision,
                 } => bodies::Body {
                     mass,
                     radius,
@@ -122,6 +353,8 @@ fn do_one_level(entry: Entry) -> SSBE {
                     color,
                     outline,
                     immovable,
+                    test_particle,
+                    collision: collision.map(Into::into),
                 },
             },
             bodies::Kinemat {
@@ -129,7 +362,7 @@ fn do_one_level(entry: Entry) -> SSBE {
                 vel: Vector2D::new(kinemat.vel.0, kinemat.vel.1),
             },
         )
-        .add_bulk(children.drain(0..).map(|kid| do_one_level(kid))),
+        .add_bulk(children.drain(0..).map(do_one_level)),
         Entry::MoonsBuilder {
             count,
             min_mass,
@@ -155,6 +388,7 @@ fn do_one_level(entry: Entry) -> SSBE {
             max_bodies,
             seed,
             clockwise,
+            test_particles,
         } => SSBE::AsteroidsBuilder {
             total_mass,
             min_orbit,
@@ -163,6 +397,7 @@ fn do_one_level(entry: Entry) -> SSBE {
             max_bodies,
             seed,
             clockwise,
+            test_particles,
         },
     }
 }
@@ -186,7 +421,7 @@ fn get_body_from_id(id: String) -> bodies::Body {
 
     lazy_static! {
         static ref BODIES: HashMap<String, fn() -> bodies::Body> = {
-            let h = maker![
+            maker![
                 sol,
                 mercury,
                 venus,
@@ -200,13 +435,11 @@ fn get_body_from_id(id: String) -> bodies::Body {
                 uranus,
                 neptune,
                 halleys_comet
-            ];
-
-            h
+            ]
         };
     }
 
     BODIES
         .get(&id)
-        .expect(&format!("No prefab body named {}", id))()
+        .unwrap_or_else(|| panic!("No prefab body named {}", id))()
 }
@@ -14,6 +14,8 @@ pub mod bodies {
             color: 0xFFDF22,
             outline: 0xE87513,
             immovable: true,
+            test_particle: false,
+            collision: None,
         }
     }
 
@@ -27,6 +29,8 @@ pub mod bodies {
             color: 0xa79ea1,   // light gray
             outline: 0x737375, // dark gray
             immovable: false,
+            test_particle: false,
+            collision: None,
         }
     }
 
@@ -39,6 +43,8 @@ pub mod bodies {
             color: 0xfcd172,   // gray yellow
             outline: 0xaf5a23, // brown
             immovable: false,
+            test_particle: false,
+            collision: None,
         }
     }
 
@@ -51,6 +57,8 @@ pub mod bodies {
             color: 0x3669FF,   // blue
             outline: 0x56FF2D, // green
             immovable: false,
+            test_particle: false,
+            collision: None,
         }
     }
 
@@ -64,6 +72,8 @@ pub mod bodies {
             color: 0x3c3a38,   // dark gray,
             outline: 0xadaca9, // light gray,
             immovable: false,
+            test_particle: false,
+            collision: None,
         }
     }
 
@@ -76,6 +86,8 @@ pub mod bodies {
             color: 0xff5c26,   // red-orange
             outline: 0xc9af9e, // gray
             immovable: false,
+            test_particle: false,
+            collision: None,
         }
     }
 
@@ -96,6 +108,8 @@ pub mod bodies {
             color: 0x977569,   // bruisey brown
             outline: 0x8b5b45, // brown red
             immovable: false,
+            test_particle: false,
+            collision: None,
         }
     }
 
@@ -108,6 +122,8 @@ pub mod bodies {
             color: 0xf5b92f,   // yellow,
             outline: 0x8c8109, // disturbingly close to urine
             immovable: false,
+            test_particle: false,
+            collision: None,
         }
     }
 
@@ -120,6 +136,8 @@ pub mod bodies {
             color: 0x48faff,   // ice blue
             outline: 0x62e4f9, // darker blue
             immovable: false,
+            test_particle: false,
+            collision: None,
         }
     }
 
@@ -132,6 +150,8 @@ pub mod bodies {
             color: 0x6e8add,   // light blue
             outline: 0xc3ddff, // lighter blue
             immovable: false,
+            test_particle: false,
+            collision: None,
         }
     }
 
@@ -143,6 +163,8 @@ pub mod bodies {
             color: 0xddddff,   // slightly blue white
             outline: 0x80b09b, //space purple
             immovable: false,
+            test_particle: false,
+            collision: None,
         }
     }
 
@@ -158,6 +180,8 @@ pub mod bodies {
             color: 0x015089,   // azure
             outline: 0xc1d8e6, // light blue
             immovable: false,
+            test_particle: false,
+            collision: None,
         }
     }
 
@@ -172,6 +196,8 @@ pub mod bodies {
             color: 0xe8b374,   // orangey brown
             outline: 0x71401d, // brown
             immovable: false,
+            test_particle: false,
+            collision: None,
         }
     }
 }
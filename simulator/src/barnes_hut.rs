@@ -0,0 +1,220 @@
+//! A quadtree for approximating gravity with the Barnes-Hut algorithm.
+//!
+//! Every node knows the total mass and center of mass of everything inside it,
+//! so a far enough away node can be treated as one big orbiter.
+
+use crate::config::SimulationConfig;
+use crate::gravity::{pull, pulls};
+use euclid::default::{Point2D, Vector2D};
+
+/// Stop splitting nodes after this many levels, so things sitting on top of each other
+/// don't recurse forever.
+const MAX_DEPTH: usize = 48;
+
+pub(crate) struct QuadTree {
+    /// All the nodes. The root is at index 0.
+    nodes: Vec<Node>,
+}
+
+struct Node {
+    center: Point2D<f64>,
+    half_width: f64,
+    mass: f64,
+    center_of_mass: Point2D<f64>,
+    /// Index of the first of four children in `nodes`, if this node was split.
+    children: Option<usize>,
+    /// The orbiters in this node, if it's a leaf.
+    orbiters: Vec<usize>,
+}
+
+impl QuadTree {
+    /// Build a tree out of every orbiter heavy enough to pull on things.
+    pub fn new(config: &SimulationConfig, pos: &[Point2D<f64>], masses: &[f64]) -> Self {
+        let pullers: Vec<usize> = (0..pos.len())
+            .filter(|&i| pulls(config, masses[i]))
+            .collect();
+
+        // Find a square that fits everything
+        let (min, max) = pullers.iter().fold(
+            (
+                Point2D::new(f64::INFINITY, f64::INFINITY),
+                Point2D::new(f64::NEG_INFINITY, f64::NEG_INFINITY),
+            ),
+            |(min, max), &i| (min.min(pos[i]), max.max(pos[i])),
+        );
+        let (center, half_width) = if pullers.is_empty() {
+            (Point2D::zero(), 1.0)
+        } else {
+            let size = max - min;
+            (min.lerp(max, 0.5), size.x.max(size.y) / 2.0)
+        };
+
+        let mut tree = QuadTree {
+            nodes: vec![Node::empty(center, half_width)],
+        };
+        tree.build(0, pullers, pos, masses, 0);
+        tree
+    }
+
+    /// Fill in the node at `slot` so it holds `orbiters`, splitting it up if needed.
+    fn build(
+        &mut self,
+        slot: usize,
+        orbiters: Vec<usize>,
+        pos: &[Point2D<f64>],
+        masses: &[f64],
+        depth: usize,
+    ) {
+        let mass: f64 = orbiters.iter().map(|&i| masses[i]).sum();
+        let node = &mut self.nodes[slot];
+        node.mass = mass;
+        if mass > 0.0 {
+            node.center_of_mass = orbiters.iter().fold(Point2D::zero(), |com, &i| {
+                com + pos[i].to_vector() * masses[i]
+            }) / mass;
+        }
+
+        if orbiters.len() <= 1 || depth >= MAX_DEPTH {
+            node.orbiters = orbiters;
+            return;
+        }
+
+        // Split into quadrants. The four children sit next to each other in `nodes`.
+        let (center, quarter) = (node.center, node.half_width / 2.0);
+        let mut quadrants: [Vec<usize>; 4] = Default::default();
+        for i in orbiters {
+            quadrants[quadrant(center, pos[i])].push(i);
+        }
+        let first_child = self.nodes.len();
+        self.nodes[slot].children = Some(first_child);
+        for q in 0..4 {
+            self.nodes
+                .push(Node::empty(quadrant_center(center, quarter, q), quarter));
+        }
+        for (q, kids) in quadrants.iter_mut().enumerate() {
+            self.build(
+                first_child + q,
+                std::mem::take(kids),
+                pos,
+                masses,
+                depth + 1,
+            );
+        }
+    }
+
+    /// The approximate acceleration on orbiter `target`.
+    pub fn acceleration(
+        &self,
+        config: &SimulationConfig,
+        target: usize,
+        pos: &[Point2D<f64>],
+        masses: &[f64],
+        theta: f64,
+    ) -> Vector2D<f64> {
+        let at = pos[target];
+        let mut acc = Vector2D::zero();
+        let mut stack = vec![0];
+        while let Some(index) = stack.pop() {
+            let node = &self.nodes[index];
+            if node.mass == 0.0 {
+                continue;
+            }
+            match node.children {
+                None => {
+                    for &i in node.orbiters.iter() {
+                        if i != target {
+                            acc += pull(config, at, pos[i], masses[i]);
+                        }
+                    }
+                }
+                Some(first_child) => {
+                    // Open the node if it looks wide from here. The offset of the center
+                    // of mass is added so lopsided nodes don't get lumped in too early.
+                    let dist = (node.center_of_mass - at).length();
+                    let width = node.half_width * 2.0;
+                    let offset = (node.center_of_mass - node.center).length();
+                    if !node.contains(at) && width < theta * (dist - offset) {
+                        // Far enough away to lump together
+                        acc += pull(config, at, node.center_of_mass, node.mass);
+                    } else {
+                        stack.extend(first_child..first_child + 4);
+                    }
+                }
+            }
+        }
+        acc
+    }
+
+    /// The closest orbiter in the tree to orbiter `target`, besides itself.
+    pub fn nearest(&self, target: usize, pos: &[Point2D<f64>]) -> Option<usize> {
+        let at = pos[target];
+        // The square of the distance to the closest one so far, and which one it is
+        let mut best: Option<(f64, usize)> = None;
+        let mut stack = vec![0];
+        while let Some(index) = stack.pop() {
+            let node = &self.nodes[index];
+            if node.mass == 0.0 {
+                continue;
+            }
+            if let Some((best_squared, _)) = best {
+                if node.square_distance_to(at) > best_squared {
+                    // Nothing in here can be any closer
+                    continue;
+                }
+            }
+            match node.children {
+                None => {
+                    for &i in node.orbiters.iter() {
+                        let dist_squared = (pos[i] - at).square_length();
+                        if i != target
+                            && best.is_none_or(|(best_squared, _)| dist_squared < best_squared)
+                        {
+                            best = Some((dist_squared, i));
+                        }
+                    }
+                }
+                Some(first_child) => stack.extend(first_child..first_child + 4),
+            }
+        }
+        best.map(|(_, i)| i)
+    }
+}
+
+impl Node {
+    /// A node with nothing in it yet
+    fn empty(center: Point2D<f64>, half_width: f64) -> Self {
+        Node {
+            center,
+            half_width,
+            mass: 0.0,
+            center_of_mass: center,
+            children: None,
+            orbiters: Vec::new(),
+        }
+    }
+
+    fn contains(&self, point: Point2D<f64>) -> bool {
+        (point.x - self.center.x).abs() <= self.half_width
+            && (point.y - self.center.y).abs() <= self.half_width
+    }
+
+    /// The square of how far a point is from the closest part of the node. 0 if it's inside.
+    fn square_distance_to(&self, point: Point2D<f64>) -> f64 {
+        let dx = ((point.x - self.center.x).abs() - self.half_width).max(0.0);
+        let dy = ((point.y - self.center.y).abs() - self.half_width).max(0.0);
+        dx * dx + dy * dy
+    }
+}
+
+/// Which quadrant of a node centered at `center` the point is in, from 0 to 3.
+fn quadrant(center: Point2D<f64>, point: Point2D<f64>) -> usize {
+    (if point.x >= center.x { 1 } else { 0 }) + (if point.y >= center.y { 2 } else { 0 })
+}
+
+/// The center of quadrant `q` of a node centered at `center`.
+fn quadrant_center(center: Point2D<f64>, quarter: f64, q: usize) -> Point2D<f64> {
+    Point2D::new(
+        center.x + if q & 1 == 1 { quarter } else { -quarter },
+        center.y + if q & 2 == 2 { quarter } else { -quarter },
+    )
+}
@@ -1,10 +1,12 @@
 //! Handles bodies and such
 
+use crate::config::CollisionModel;
 use euclid::default::{Point2D, Vector2D};
+use serde::{Deserialize, Serialize};
 
 /// The representation of a body, like a star, planet, comet...
 /// Doesn't store its position or velocity.
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Body {
     pub mass: f64,
     pub radius: f64,
@@ -16,10 +18,26 @@ pub struct Body {
     pub name: String,
     /// If it doesn't move regardless of gravity
     pub immovable: bool,
+    /// If it's a test particle: it gets pulled on like anything else, but counts as massless,
+    /// so it never pulls on anything and never merges into anything
+    pub test_particle: bool,
+    /// What happens when it hits something, if it's different from the rest of the simulation
+    pub collision: Option<CollisionModel>,
+}
+
+impl Body {
+    /// How heavy it is as far as gravity is concerned. Test particles don't weigh anything.
+    pub fn gravitating_mass(&self) -> f64 {
+        if self.test_particle {
+            0.0
+        } else {
+            self.mass
+        }
+    }
 }
 
 /// A Kinemat holds all the kinematic information about something.
-#[derive(Copy, Clone, Debug)]
+#[derive(Copy, Clone, Debug, Serialize, Deserialize)]
 pub struct Kinemat {
     pub pos: Point2D<f64>,
     pub vel: Vector2D<f64>,
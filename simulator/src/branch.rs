@@ -0,0 +1,260 @@
+//! Handles keeping every timeline around when the simulation goes back to an old save.
+//!
+//! Only the branch being simulated lives in the SolarSystem itself.
+//! Every other branch is parked, with its own saves and everything it needs to carry on where it left off.
+
+use crate::bodies::Kinemat;
+use crate::history::{History, HistoryError};
+use crate::lineage::Lineage;
+use crate::registry::BodyId;
+use crate::storage::Storage;
+use crate::SolarSystem;
+use euclid::default::Vector2D;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::mem;
+
+/// One of the timelines.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct BranchInfo {
+    /// Which branch it is. 0 is the one the simulation started on.
+    pub index: usize,
+    /// The branch it split off from, if it isn't the first one
+    pub parent: Option<usize>,
+    /// Which step it split off from its parent on
+    pub fork_step: usize,
+    /// How many seconds into the simulation it split off
+    pub fork_time: f64,
+    /// The step it's up to
+    pub step: usize,
+    /// How many seconds it's simulated
+    pub time: f64,
+    /// Whether it's the one being simulated right now
+    pub current: bool,
+}
+
+/// How the ends of two branches differ.
+#[derive(Clone, Debug)]
+pub struct BranchDiff {
+    /// How many more seconds the second branch has simulated than the first
+    pub time_difference: f64,
+    /// Everything alive at the end of both, and how far apart it ended up
+    pub bodies: Vec<BodyDiff>,
+    /// The IDs and names of things only alive at the end of the first branch
+    pub only_in_first: Vec<(BodyId, String)>,
+    /// The IDs and names of things only alive at the end of the second branch
+    pub only_in_second: Vec<(BodyId, String)>,
+}
+
+/// How one body differs between the ends of two branches.
+#[derive(Clone, Debug)]
+pub struct BodyDiff {
+    pub id: BodyId,
+    /// Its name
+    pub name: String,
+    /// Where it is in the second branch compared to the first
+    pub offset: Vector2D<f64>,
+    /// How fast it's going in the second branch compared to the first
+    pub velocity_offset: Vector2D<f64>,
+}
+
+#[derive(Clone, Serialize, Deserialize)]
+pub(crate) struct Branches {
+    /// Which one is being simulated
+    current: usize,
+    branches: Vec<Branch>,
+}
+
+#[derive(Clone, Serialize, Deserialize)]
+struct Branch {
+    parent: Option<usize>,
+    fork_step: usize,
+    fork_time: f64,
+    /// Everything about the branch, unless it's the current one
+    parked: Option<Parked>,
+}
+
+/// A branch that isn't being simulated right now.
+#[derive(Clone, Serialize, Deserialize)]
+pub(crate) struct Parked {
+    live: Vec<(BodyId, Kinemat)>,
+    history: History,
+    frames_elapsed: usize,
+    time: f64,
+    lineage: Lineage,
+    /// What was on rails, and around what
+    rails: BTreeMap<BodyId, BodyId>,
+}
+
+impl Default for Branches {
+    fn default() -> Self {
+        Branches {
+            current: 0,
+            branches: vec![Branch {
+                parent: None,
+                fork_step: 0,
+                fork_time: 0.0,
+                parked: None,
+            }],
+        }
+    }
+}
+
+impl Branches {
+    /// Park the current branch and start a new one that splits off from it.
+    pub fn fork(&mut self, parked: Parked, step: usize, time: f64) {
+        self.branches[self.current].parked = Some(parked);
+        self.branches.push(Branch {
+            parent: Some(self.current),
+            fork_step: step,
+            fork_time: time,
+            parked: None,
+        });
+        self.current = self.branches.len() - 1;
+    }
+
+    /// Every ID a parked branch needs, possibly more than once.
+    pub fn ids(&self) -> impl Iterator<Item = BodyId> + '_ {
+        self.branches
+            .iter()
+            .filter_map(|branch| branch.parked.as_ref())
+            .flat_map(|parked| {
+                let live = parked.live.iter().map(|&(id, _)| id);
+                live.chain(parked.history.ids())
+            })
+    }
+
+    fn check(&self, index: usize) -> Result<(), HistoryError> {
+        if index < self.branches.len() {
+            Ok(())
+        } else {
+            Err(HistoryError::NoSuchBranch {
+                index,
+                count: self.branches.len(),
+            })
+        }
+    }
+}
+
+impl SolarSystem {
+    /// Every timeline, in the order they were made.
+    pub fn list_branches(&self) -> Vec<BranchInfo> {
+        self.branches
+            .branches
+            .iter()
+            .enumerate()
+            .map(|(index, branch)| {
+                let (step, time) = match &branch.parked {
+                    Some(parked) => (parked.frames_elapsed, parked.time),
+                    None => (self.frames_elapsed, self.time),
+                };
+                BranchInfo {
+                    index,
+                    parent: branch.parent,
+                    fork_step: branch.fork_step,
+                    fork_time: branch.fork_time,
+                    step,
+                    time,
+                    current: index == self.branches.current,
+                }
+            })
+            .collect()
+    }
+
+    /// Which branch is being simulated
+    pub fn current_branch(&self) -> usize {
+        self.branches.current
+    }
+
+    /// Park the branch being simulated and carry on with another one from where it was left.
+    pub fn switch_branch(&mut self, index: usize) -> Result<(), HistoryError> {
+        self.branches.check(index)?;
+        if index == self.branches.current {
+            return Ok(());
+        }
+        let parked = self.park();
+        let current = self.branches.current;
+        self.branches.branches[current].parked = Some(parked);
+        // Only the current branch isn't parked
+        let parked = self.branches.branches[index].parked.take().unwrap();
+        self.branches.current = index;
+
+        let mut live = Storage::default();
+        for (id, kmat) in parked.live {
+            live.push(id, &self.bodies[id], kmat);
+        }
+        self.live = live;
+        self.history = parked.history;
+        self.frames_elapsed = parked.frames_elapsed;
+        self.time = parked.time;
+        self.lineage = parked.lineage;
+        self.rails = parked.rails;
+        self.collision_events.clear();
+        Ok(())
+    }
+
+    /// Compare where everything ended up at the ends of two branches.
+    pub fn diff_branches(&self, first: usize, second: usize) -> Result<BranchDiff, HistoryError> {
+        let (first_live, first_time) = self.branch_end(first)?;
+        let (second_live, second_time) = self.branch_end(second)?;
+        let name = |id: BodyId| self.bodies[id].name.clone();
+
+        let mut bodies = Vec::new();
+        let mut only_in_first = Vec::new();
+        for (&id, first_kmat) in first_live.iter() {
+            match second_live.get(&id) {
+                Some(second_kmat) => bodies.push(BodyDiff {
+                    id,
+                    name: name(id),
+                    offset: second_kmat.pos - first_kmat.pos,
+                    velocity_offset: second_kmat.vel - first_kmat.vel,
+                }),
+                None => only_in_first.push((id, name(id))),
+            }
+        }
+        let only_in_second = second_live
+            .keys()
+            .filter(|id| !first_live.contains_key(id))
+            .map(|&id| (id, name(id)))
+            .collect();
+
+        Ok(BranchDiff {
+            time_difference: second_time - first_time,
+            bodies,
+            only_in_first,
+            only_in_second,
+        })
+    }
+
+    /// Keep what's being simulated around as its own branch, and start a new one
+    /// that splits off at `step`. The caller has to actually go back to `step`.
+    pub(crate) fn branch_off(&mut self, step: usize, time: f64) {
+        let parked = self.park();
+        // The new branch starts out with everything the old one had
+        self.history = parked.history.clone();
+        self.lineage = parked.lineage.clone();
+        self.rails = parked.rails.clone();
+        self.branches.fork(parked, step, time);
+    }
+
+    /// Take everything about the current branch out of the SolarSystem.
+    fn park(&mut self) -> Parked {
+        Parked {
+            live: self.live.kinemats().collect(),
+            history: mem::take(&mut self.history),
+            frames_elapsed: self.frames_elapsed,
+            time: self.time,
+            lineage: mem::take(&mut self.lineage),
+            rails: mem::take(&mut self.rails),
+        }
+    }
+
+    /// Where everything is at the end of a branch, and how long it's been simulated
+    fn branch_end(&self, index: usize) -> Result<(BTreeMap<BodyId, Kinemat>, f64), HistoryError> {
+        self.branches.check(index)?;
+        Ok(match &self.branches.branches[index].parked {
+            Some(parked) => (parked.live.iter().copied().collect(), parked.time),
+            None => (self.live.kinemats().collect(), self.time),
+        })
+    }
+}
@@ -0,0 +1,207 @@
+//! Handles turning seconds of simulation into real dates.
+//!
+//! Dates are Julian dates in the TDB time scale, which is what ephemerides use,
+//! shown on the (proleptic) Gregorian calendar.
+
+use serde::{Deserialize, Serialize};
+use std::fmt;
+use std::str::FromStr;
+
+const SECONDS_PER_DAY: f64 = 86_400.0;
+const MILLIS_PER_DAY: i64 = 86_400_000;
+
+/// A moment in time, as a Julian date.
+#[derive(Copy, Clone, Debug, PartialEq, PartialOrd, Serialize, Deserialize)]
+pub struct Epoch {
+    /// Days since noon on January 1st, 4713 BC
+    pub julian_date: f64,
+}
+
+/// A moment in time, the way people write it.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct CalendarDate {
+    pub year: i64,
+    /// 1 to 12
+    pub month: u32,
+    /// 1 to 31
+    pub day: u32,
+    pub hour: u32,
+    pub minute: u32,
+    /// Including fractions of a second, to the millisecond
+    pub second: f64,
+}
+
+/// A string that isn't a date `Epoch` understands.
+#[derive(Clone, Debug, PartialEq)]
+pub struct ParseEpochError(String);
+
+impl Epoch {
+    /// Noon on January 1st, 2000, which is what most modern ephemerides count from
+    pub const J2000: Epoch = Epoch {
+        julian_date: 2_451_545.0,
+    };
+
+    pub fn from_julian_date(julian_date: f64) -> Self {
+        Epoch { julian_date }
+    }
+
+    /// The moment a calendar date happened
+    pub fn from_calendar(date: CalendarDate) -> Self {
+        // Meeus, Astronomical Algorithms, chapter 7. Treat January and February as the end of the previous year
+        let (year, month) = if date.month <= 2 {
+            (date.year - 1, date.month + 12)
+        } else {
+            (date.year, date.month)
+        };
+        let century = year.div_euclid(100);
+        let leap_fix = 2 - century + century.div_euclid(4);
+        let days = (365.25 * (year + 4716) as f64).floor() + (30.6001 * (month + 1) as f64).floor();
+        let day = date.day as f64
+            + (date.hour as f64 * 3600.0 + date.minute as f64 * 60.0 + date.second)
+                / SECONDS_PER_DAY;
+        Epoch {
+            julian_date: days + day + leap_fix as f64 - 1524.5,
+        }
+    }
+
+    /// The moment `seconds` after this one
+    pub fn after(&self, seconds: f64) -> Self {
+        Epoch {
+            julian_date: self.julian_date + seconds / SECONDS_PER_DAY,
+        }
+    }
+
+    /// How many seconds after `earlier` this is
+    pub fn seconds_since(&self, earlier: Epoch) -> f64 {
+        (self.julian_date - earlier.julian_date) * SECONDS_PER_DAY
+    }
+
+    /// What the date was
+    pub fn calendar(&self) -> CalendarDate {
+        // Round to the millisecond first, so noon doesn't come out as 11:59:59.999
+        let millis = ((self.julian_date + 0.5) * MILLIS_PER_DAY as f64).round() as i64;
+        let whole_days = millis.div_euclid(MILLIS_PER_DAY);
+        let millis_of_day = millis.rem_euclid(MILLIS_PER_DAY);
+
+        // Meeus again, the other way around
+        let alpha = ((whole_days as f64 - 1_867_216.25) / 36_524.25).floor() as i64;
+        let a = whole_days + 1 + alpha - alpha.div_euclid(4);
+        let b = a + 1524;
+        let c = ((b as f64 - 122.1) / 365.25).floor() as i64;
+        let d = (365.25 * c as f64).floor() as i64;
+        let e = ((b - d) as f64 / 30.6001).floor() as i64;
+        let day = b - d - (30.6001 * e as f64).floor() as i64;
+        let month = if e < 14 { e - 1 } else { e - 13 };
+        let year = if month > 2 { c - 4716 } else { c - 4715 };
+
+        CalendarDate {
+            year,
+            month: month as u32,
+            day: day as u32,
+            hour: (millis_of_day / 3_600_000) as u32,
+            minute: (millis_of_day / 60_000 % 60) as u32,
+            second: (millis_of_day % 60_000) as f64 / 1000.0,
+        }
+    }
+}
+
+impl fmt::Display for Epoch {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{} TDB", self.calendar())
+    }
+}
+
+impl fmt::Display for CalendarDate {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "{:04}-{:02}-{:02} {:02}:{:02}:{:02}",
+            self.year,
+            self.month,
+            self.day,
+            self.hour,
+            self.minute,
+            self.second.floor()
+        )
+    }
+}
+
+/// Reads `J2000`, `2024-03-01`, `2024-03-01 06:30:00`, or `2024-03-01T06:30:00`.
+impl FromStr for Epoch {
+    type Err = ParseEpochError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let s = s.trim();
+        if s.eq_ignore_ascii_case("J2000") {
+            return Ok(Epoch::J2000);
+        }
+        let error = || ParseEpochError(s.to_string());
+        let (date, time) = match s.find(['T', ' ']) {
+            Some(split) => (&s[..split], Some(&s[split + 1..])),
+            None => (s, None),
+        };
+
+        // The year can be negative, so split the month and day off the end
+        let mut parts = date.rsplitn(3, '-');
+        let day = parts.next().and_then(|day| day.parse().ok());
+        let month = parts.next().and_then(|month| month.parse().ok());
+        let year = parts.next().and_then(|year| year.parse().ok());
+        let (year, month, day) = match (year, month, day) {
+            (Some(year), Some(month @ 1..=12), Some(day))
+                if (1..=days_in_month(year, month)).contains(&day) =>
+            {
+                (year, month, day)
+            }
+            _ => return Err(error()),
+        };
+
+        let (hour, minute, second) = match time {
+            None => (0, 0, 0.0),
+            Some(time) => {
+                let mut parts = time.splitn(3, ':');
+                let hour = parts.next().and_then(|hour| hour.parse().ok());
+                let minute = parts.next().and_then(|minute| minute.parse().ok());
+                let second = parts.next().map_or(Some(0.0), |second| second.parse().ok());
+                match (hour, minute, second) {
+                    (Some(hour @ 0..=23), Some(minute @ 0..=59), Some(second))
+                        if (0.0..61.0).contains(&second) =>
+                    {
+                        (hour, minute, second)
+                    }
+                    _ => return Err(error()),
+                }
+            }
+        };
+
+        Ok(Epoch::from_calendar(CalendarDate {
+            year,
+            month,
+            day,
+            hour,
+            minute,
+            second,
+        }))
+    }
+}
+
+/// How many days a month has, going by the Gregorian calendar's leap years.
+fn days_in_month(year: i64, month: u32) -> u32 {
+    match month {
+        4 | 6 | 9 | 11 => 30,
+        2 if year % 4 == 0 && (year % 100 != 0 || year % 400 == 0) => 29,
+        2 => 28,
+        _ => 31,
+    }
+}
+
+impl fmt::Display for ParseEpochError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "`{}` isn't a date; try something like `J2000` or `2000-01-01 12:00:00`",
+            self.0
+        )
+    }
+}
+
+impl std::error::Error for ParseEpochError {}
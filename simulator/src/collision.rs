@@ -0,0 +1,329 @@
+//! Handles what happens when orbiters run into each other.
+
+use crate::bodies::{Body, Kinemat, Orbiter};
+use crate::config::CollisionModel;
+use crate::fragmentation::fragment;
+use crate::{BodyId, SolarSystem};
+use euclid::default::Vector2D;
+
+use std::collections::HashSet;
+
+/// Something that happened when two orbiters touched.
+/// Get these from `SolarSystem::drain_collision_events`.
+#[derive(Clone, Debug)]
+pub struct CollisionEvent {
+    /// Which step it happened on
+    pub step: usize,
+    /// How many seconds into the simulation it happened
+    pub time: f64,
+    /// The two orbiters that collided
+    pub bodies: (BodyId, BodyId),
+    /// What happened to them
+    pub kind: CollisionKind,
+    /// Everything that came out of the collision.
+    /// For a merge this is just the new orbiter; when things fragment, the largest remnant comes first.
+    pub results: Vec<BodyId>,
+    /// How fast they were going relative to each other when they hit, in m/s
+    pub impact_speed: f64,
+    /// How much kinetic energy disappeared into heat, noise, and so on, in joules.
+    /// Anything immovable counts as having none.
+    pub energy_released: f64,
+}
+
+/// What happened in a collision.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum CollisionKind {
+    /// They stuck together into a new orbiter
+    Merged,
+    /// They smashed into a remnant and a bunch of debris
+    Fragmented,
+    /// They bounced off each other, and are both still around
+    Bounced,
+    /// The lighter one got deleted
+    Destroyed,
+}
+
+impl SolarSystem {
+    /// Find everything that's touching and deal with it according to the collision model.
+    /// Each orbiter can only collide once per step; anything else it touches gets it next step.
+    /// When something touches several things at once, it hits whichever it overlaps most,
+    /// so the result only depends on where things are and not on what order they're stored in.
+    pub(crate) fn collide(&mut self) {
+        // Slots of every pair of orbiters that are touching, how deep they overlap
+        // as a fraction of their combined radii, and what to do about it
+        let mut touching: Vec<(usize, usize, f64, CollisionModel)> = Vec::new();
+
+        // Sweep along the x axis so we only check things that overlap horizontally.
+        // Otherwise big asteroid belts spend all their time here.
+        let live = &self.live;
+        let mut by_left_edge: Vec<usize> = (0..live.len()).collect();
+        let left_edge = |slot: usize| live.pos[slot].x - live.radius[slot];
+        by_left_edge.sort_by(|&a, &b| {
+            left_edge(a)
+                .total_cmp(&left_edge(b))
+                .then(live.ids[a].cmp(&live.ids[b]))
+        });
+
+        for (n, &slot) in by_left_edge.iter().enumerate() {
+            let right_edge = live.pos[slot].x + live.radius[slot];
+
+            for &other in by_left_edge[n + 1..].iter() {
+                if left_edge(other) > right_edge {
+                    // Everything after this is even further right
+                    break;
+                }
+                if live.immovable[slot] && live.immovable[other] {
+                    // Two things that don't move can't crash into each other
+                    continue;
+                }
+                if live.test_particle[slot] && live.test_particle[other] {
+                    // Test particles don't even notice each other
+                    continue;
+                }
+
+                let reach = live.radius[slot] + live.radius[other];
+                let dist_squared = (live.pos[other] - live.pos[slot]).square_length();
+                if dist_squared < reach * reach {
+                    // ooh, a collision!
+                    let mut model = self.collision_model(live.ids[slot], live.ids[other]);
+                    if model == CollisionModel::PassThrough {
+                        continue;
+                    }
+                    if live.test_particle[slot] || live.test_particle[other] {
+                        // A test particle can't merge or push anything around, so it just gets deleted.
+                        // It doesn't weigh anything, so it's always the smaller one.
+                        model = CollisionModel::DestroySmaller;
+                    }
+                    let depth = 1.0 - dist_squared.sqrt() / reach;
+                    touching.push((slot, other, depth, model));
+                }
+            }
+        }
+
+        // Deepest first. Ties go to whichever pair has the oldest IDs.
+        let pair_ids = |slot: usize, other: usize| {
+            let (a, b) = (live.ids[slot], live.ids[other]);
+            (a.min(b), a.max(b))
+        };
+        touching.sort_by(|&(a1, a2, depth_a, _), &(b1, b2, depth_b, _)| {
+            depth_b
+                .total_cmp(&depth_a)
+                .then(pair_ids(a1, a2).cmp(&pair_ids(b1, b2)))
+        });
+
+        // Slots of each pair of orbiters that collided, and what to do about it
+        let mut collisions: Vec<(usize, usize, CollisionModel)> = Vec::new();
+        // IDs of things that have already hit something else this step.
+        let mut collided_ids: HashSet<BodyId> = HashSet::new();
+        for (slot, other, _, model) in touching {
+            let (id, other_id) = (live.ids[slot], live.ids[other]);
+            if collided_ids.contains(&id) || collided_ids.contains(&other_id) {
+                continue;
+            }
+            collided_ids.insert(id);
+            collided_ids.insert(other_id);
+            collisions.push((slot, other, model));
+        }
+
+        // IDs of things to stop processing
+        let mut doomed: Vec<BodyId> = Vec::new();
+        // Stores any new orbiters formed by collision, along with which event they came from
+        let mut new_orbiters: Vec<(usize, Orbiter)> = Vec::new();
+        for (slot, other, model) in collisions {
+            let (id, other_id) = (self.live.ids[slot], self.live.ids[other]);
+            let energy_before = self.live.kinetic_energy(slot) + self.live.kinetic_energy(other);
+            let mut event = CollisionEvent {
+                step: self.frames_elapsed,
+                time: self.time,
+                bodies: (id, other_id),
+                kind: CollisionKind::Merged,
+                results: Vec::new(),
+                impact_speed: (self.live.vel[other] - self.live.vel[slot]).length(),
+                energy_released: energy_before,
+            };
+            let event_index = self.collision_events.len();
+            match model {
+                CollisionModel::Merge => {
+                    let (body, kmat) = (&self.bodies[id], self.live.kinemat(slot));
+                    let (other_body, other_kmat) =
+                        (&self.bodies[other_id], self.live.kinemat(other));
+                    // Hit hard enough, things smash instead of sticking together
+                    let debris = self.config.fragmentation.and_then(|fragmentation| {
+                        fragment(
+                            &self.config,
+                            &fragmentation,
+                            debris_seed(fragmentation.seed, self.frames_elapsed, id, other_id),
+                            (body, &kmat),
+                            (other_body, &other_kmat),
+                        )
+                    });
+                    let pieces = match debris {
+                        Some(debris) => {
+                            event.kind = CollisionKind::Fragmented;
+                            debris
+                        }
+                        None => vec![merge(body, &kmat, other_body, &other_kmat)],
+                    };
+                    for piece in pieces {
+                        event.energy_released -= 0.5 * piece.0.mass * piece.1.vel.square_length();
+                        new_orbiters.push((event_index, piece));
+                    }
+                    doomed.push(id);
+                    doomed.push(other_id);
+                }
+                CollisionModel::Elastic { restitution } => {
+                    self.bounce(slot, other, restitution);
+                    event.kind = CollisionKind::Bounced;
+                    event.results = vec![id, other_id];
+                    event.energy_released -=
+                        self.live.kinetic_energy(slot) + self.live.kinetic_energy(other);
+                }
+                CollisionModel::DestroySmaller => {
+                    let (loser, winner) = if self.live.mass[slot] < self.live.mass[other] {
+                        (id, other)
+                    } else {
+                        (other_id, slot)
+                    };
+                    doomed.push(loser);
+                    event.kind = CollisionKind::Destroyed;
+                    event.results = vec![self.live.ids[winner]];
+                    event.energy_released -= self.live.kinetic_energy(winner);
+                }
+                CollisionModel::PassThrough => {}
+            }
+            self.collision_events.push(event);
+        }
+
+        self.live.remove(&doomed);
+        for (event_index, new_orbiter) in new_orbiters {
+            // Add a shiny new orbiter!
+            let new_id = self.add_orbiter(new_orbiter);
+            let event = &mut self.collision_events[event_index];
+            event.results.push(new_id);
+            let (id, other_id) = event.bodies;
+            self.lineage.record(
+                new_id,
+                &[(id, &self.bodies[id]), (other_id, &self.bodies[other_id])],
+                event.step,
+                event.time,
+            );
+        }
+    }
+
+    /// Work out what should happen when these two collide.
+    /// If the heavier one has its own model, that wins; otherwise the lighter one's is used,
+    /// and if neither has one it's whatever the config says.
+    fn collision_model(&self, id: BodyId, other_id: BodyId) -> CollisionModel {
+        let (body, other_body) = (&self.bodies[id], &self.bodies[other_id]);
+        let (heavy, light) = if body.mass >= other_body.mass {
+            (body, other_body)
+        } else {
+            (other_body, body)
+        };
+        heavy
+            .collision
+            .or(light.collision)
+            .unwrap_or(self.config.collisions)
+    }
+
+    /// Bounce two orbiters off each other.
+    /// `restitution` is how much of their closing speed they keep: 1 is perfectly bouncy, 0 is splat.
+    /// Immovable things act like they're infinitely heavy.
+    fn bounce(&mut self, slot: usize, other: usize, restitution: f64) {
+        let live = &mut self.live;
+        let inv_mass = |slot: usize| {
+            if live.immovable[slot] {
+                0.0
+            } else {
+                live.mass[slot].recip()
+            }
+        };
+        let (inv_mass_1, inv_mass_2) = (inv_mass(slot), inv_mass(other));
+        let total_inv_mass = inv_mass_1 + inv_mass_2;
+
+        let delta = live.pos[other] - live.pos[slot];
+        let dist = delta.length();
+        // Points from the first to the second. If they're exactly on top of each other, pick something.
+        let normal = if dist > 0.0 {
+            delta / dist
+        } else {
+            Vector2D::new(1.0, 0.0)
+        };
+
+        // Push them apart so they don't keep colliding, the light one moving the most
+        let overlap = live.radius[slot] + live.radius[other] - dist;
+        live.pos[slot] -= normal * (overlap * inv_mass_1 / total_inv_mass);
+        live.pos[other] += normal * (overlap * inv_mass_2 / total_inv_mass);
+
+        let closing_speed = (live.vel[other] - live.vel[slot]).dot(normal);
+        if closing_speed >= 0.0 {
+            // Already flying apart
+            return;
+        }
+        let impulse = -(1.0 + restitution) * closing_speed / total_inv_mass;
+        live.vel[slot] -= normal * (impulse * inv_mass_1);
+        live.vel[other] += normal * (impulse * inv_mass_2);
+    }
+}
+
+/// Combine two touching orbiters into one.
+pub(crate) fn merge(
+    body: &Body,
+    kmat: &Kinemat,
+    other_body: &Body,
+    other_kmat: &Kinemat,
+) -> Orbiter {
+    Orbiter(
+        Body {
+            mass: body.mass + other_body.mass,
+            // Combine the radii as if they were actually spheres instead of just adding them.
+            radius: (body.radius.powi(3) + other_body.radius.powi(3)).cbrt(),
+            name: format!("{} & {}", body.name, other_body.name),
+            color: mix_colors(body.color, body.mass, other_body.color, other_body.mass),
+            outline: mix_colors(body.outline, body.mass, other_body.outline, other_body.mass),
+            immovable: body.immovable || other_body.immovable, // If either of them doesn't move, neither does this one
+            // Test particles never merge, so this is only here to be complete
+            test_particle: false,
+            // Whatever the heavier one did, this does too
+            collision: if body.mass >= other_body.mass {
+                body.collision
+            } else {
+                other_body.collision
+            },
+        },
+        Kinemat::new(
+            // Pos at center of mass
+            (kmat.pos * body.mass + (other_kmat.pos * other_body.mass).to_vector())
+                / (body.mass + other_body.mass),
+            // Momentum (mass * vel) is conserved!
+            if !body.immovable && !other_body.immovable {
+                (kmat.vel * body.mass + other_kmat.vel * other_body.mass)
+                    / (body.mass + other_body.mass)
+            } else {
+                Vector2D::zero()
+            },
+        ),
+    )
+}
+
+/// Mix the config's seed with when and what collided, so every collision scatters its debris
+/// differently but running the same simulation twice gives the same debris.
+fn debris_seed(seed: u64, frame: usize, id: BodyId, other_id: BodyId) -> u64 {
+    [frame as u64, id.index() as u64, other_id.index() as u64]
+        .iter()
+        .fold(seed, |hash, &part| {
+            (hash ^ part).wrapping_mul(0x100_0000_01b3) // FNV prime
+        })
+}
+
+// Interpolate two colors with a weighted average of the masses
+fn mix_colors(c1: u32, w1: f64, c2: u32, w2: f64) -> u32 {
+    [0x0000ff, 0x00ff00, 0xff0000]
+        .iter()
+        .fold(0, |wip_color, mask| {
+            let comp1 = c1 & mask;
+            let comp2 = c2 & mask;
+            let color = ((comp1 as f64 * w1 + comp2 as f64 * w2) / (w1 + w2)) as u32;
+            wip_color + (color & mask)
+        })
+}
@@ -0,0 +1,215 @@
+//! Handles the knobs for how a SolarSystem simulates things.
+
+use crate::clock::Epoch;
+use crate::GRAV_CONSTANT;
+use serde::{Deserialize, Serialize};
+
+/// All the physical constants and policies a SolarSystem runs with.
+/// Use `SimulationConfig::default()` for sensible values,
+/// or `SimulationConfig::builder()` to change a few of them.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct SimulationConfig {
+    /// The gravitational constant, in m^3 / (kg s^2)
+    pub grav_constant: f64,
+    /// Any masses under this amount don't bother pulling on others (but do get pulled).
+    /// This used to be 1e23, but it was always switched off, so now it defaults to 0.
+    pub min_pull_mass: f64,
+    /// Any masses farther than this amount away don't pull on each other.
+    /// The default is about 5x as far as Halley's comet is at the max.
+    pub max_pull_distance: f64,
+    /// How gravity gets weaker at close range, instead of going to infinity.
+    pub softening: Softening,
+    /// How often to save, and how much history to keep.
+    pub history: HistoryPolicy,
+    /// What to do when two things touch.
+    pub collisions: CollisionModel,
+    /// If set, merging collisions that are violent enough smash things into debris instead.
+    pub fragmentation: Option<Fragmentation>,
+    /// The date the simulation starts at, if it's meant to be a real one.
+    pub epoch: Option<Epoch>,
+    /// If set, everything lighter than this starts out on rails around whatever it orbits,
+    /// following a fixed Kepler orbit instead of being integrated.
+    pub on_rails_below: Option<f64>,
+}
+
+impl Default for SimulationConfig {
+    fn default() -> Self {
+        SimulationConfig {
+            grav_constant: GRAV_CONSTANT,
+            min_pull_mass: 0.0,
+            max_pull_distance: 51e13,
+            softening: Softening::None,
+            history: HistoryPolicy::default(),
+            collisions: CollisionModel::default(),
+            fragmentation: None,
+            epoch: None,
+            on_rails_below: None,
+        }
+    }
+}
+
+impl SimulationConfig {
+    /// Start building a config from the defaults.
+    pub fn builder() -> SimulationConfigBuilder {
+        SimulationConfigBuilder {
+            config: SimulationConfig::default(),
+        }
+    }
+}
+
+/// How the backups of a simulation are kept.
+#[derive(Copy, Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct HistoryPolicy {
+    /// Save once every this many simulation steps.
+    pub save_every: usize,
+    /// With `update_adaptive`, save once every this many seconds of simulated time instead,
+    /// since the steps can be any size.
+    pub save_every_seconds: f64,
+    /// Keep at most this many saves
+    pub save_count: usize,
+    /// Keep the saves under about this many bytes of memory.
+    /// When there's too many saves or too many bytes, older history gets thinned out
+    /// so it's spaced further apart, instead of just being thrown away.
+    pub memory_budget: usize,
+    /// Store a full keyframe once every this many saves.
+    /// The saves in between just store how far things moved since the keyframe,
+    /// which takes half the memory but is only accurate to about 1 part in 10 million of that distance.
+    pub keyframe_every: usize,
+}
+
+impl Default for HistoryPolicy {
+    fn default() -> Self {
+        HistoryPolicy {
+            save_every: 1_000,
+            // About three years
+            save_every_seconds: 1e8,
+            save_count: 1_000,
+            memory_budget: 256 * 1024 * 1024,
+            keyframe_every: 16,
+        }
+    }
+}
+
+/// How gravity is weakened when things get close, so close passes don't fling things around with huge accelerations.
+/// This lets crowded scenarios like star clusters use big steps.
+#[derive(Copy, Clone, Debug, PartialEq, Default, Serialize, Deserialize)]
+pub enum Softening {
+    /// Exact Newtonian gravity all the way down.
+    #[default]
+    None,
+    /// Plummer softening: r^2 becomes r^2 + length^2.
+    /// Simple, but gravity is a little weaker than it should be even well past `length`.
+    Plummer { length: f64 },
+    /// The cubic spline kernel GADGET uses. Gravity is exactly Newtonian past `length`,
+    /// and smoothly drops to nothing inside it.
+    /// It's about as soft as Plummer softening with a length 2.8 times smaller.
+    Spline { length: f64 },
+}
+
+/// What happens when two orbiters touch.
+/// This is set for the whole simulation in the config, and can be overridden for each Body.
+#[derive(Copy, Clone, Debug, PartialEq, Default, Serialize, Deserialize)]
+pub enum CollisionModel {
+    /// Squish them together into one orbiter, conserving mass and momentum.
+    #[default]
+    Merge,
+    /// Bounce them off each other.
+    /// `restitution` is how much of their closing speed they keep: 1 is perfectly bouncy.
+    Elastic { restitution: f64 },
+    /// Let them fly right through each other.
+    PassThrough,
+    /// Delete the lighter one. The heavier one doesn't notice.
+    DestroySmaller,
+}
+
+/// How violent collisions break things apart.
+///
+/// How hard something got hit is measured by the specific impact energy:
+/// the kinetic energy of the impact in the center of mass frame, divided by the total mass.
+/// Above `threshold`, only `threshold / energy` of the mass stays in the largest remnant,
+/// and the rest is broken into debris whose masses follow a power law.
+#[derive(Copy, Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Fragmentation {
+    /// Specific impact energy, in J/kg, where things start breaking apart.
+    pub threshold: f64,
+    /// Most pieces of debris a single collision can make.
+    pub max_fragments: usize,
+    /// The exponent of the debris mass distribution. The number of pieces heavier than `m` goes like `m^-power_law_index`.
+    /// Bigger numbers mean more small pieces.
+    pub power_law_index: f64,
+    /// Debris lighter than this isn't worth simulating.
+    /// If there isn't enough debris mass for even one piece this heavy, things just merge.
+    pub min_fragment_mass: f64,
+    /// Seed for the random numbers used to scatter the debris.
+    pub seed: u64,
+}
+
+impl Default for Fragmentation {
+    fn default() -> Self {
+        Fragmentation {
+            threshold: 1e6,
+            max_fragments: 20,
+            // Dohnanyi's collisional cascade
+            power_law_index: 0.83,
+            min_fragment_mass: 1e15,
+            seed: 0,
+        }
+    }
+}
+
+/// Lets you construct a SimulationConfig a piece at a time.
+pub struct SimulationConfigBuilder {
+    config: SimulationConfig,
+}
+
+impl SimulationConfigBuilder {
+    pub fn grav_constant(&mut self, grav_constant: f64) -> &mut Self {
+        self.config.grav_constant = grav_constant;
+        self
+    }
+
+    pub fn min_pull_mass(&mut self, min_pull_mass: f64) -> &mut Self {
+        self.config.min_pull_mass = min_pull_mass;
+        self
+    }
+
+    pub fn max_pull_distance(&mut self, max_pull_distance: f64) -> &mut Self {
+        self.config.max_pull_distance = max_pull_distance;
+        self
+    }
+
+    pub fn softening(&mut self, softening: Softening) -> &mut Self {
+        self.config.softening = softening;
+        self
+    }
+
+    pub fn history(&mut self, history: HistoryPolicy) -> &mut Self {
+        self.config.history = history;
+        self
+    }
+
+    pub fn collisions(&mut self, collisions: CollisionModel) -> &mut Self {
+        self.config.collisions = collisions;
+        self
+    }
+
+    pub fn fragmentation(&mut self, fragmentation: Option<Fragmentation>) -> &mut Self {
+        self.config.fragmentation = fragmentation;
+        self
+    }
+
+    pub fn epoch(&mut self, epoch: Option<Epoch>) -> &mut Self {
+        self.config.epoch = epoch;
+        self
+    }
+
+    pub fn on_rails_below(&mut self, on_rails_below: Option<f64>) -> &mut Self {
+        self.config.on_rails_below = on_rails_below;
+        self
+    }
+
+    /// Get the finished config. The builder can keep being used afterwards.
+    pub fn build(&self) -> SimulationConfig {
+        self.config.clone()
+    }
+}
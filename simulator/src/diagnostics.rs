@@ -0,0 +1,103 @@
+//! Handles working out the things that should stay the same, to see how much they don't.
+//!
+//! Everything here looks at what's being simulated right now, even while looking at a save.
+
+use crate::gravity;
+use crate::SolarSystem;
+use euclid::default::{Point2D, Vector2D};
+
+impl SolarSystem {
+    /// The total kinetic energy of everything, in joules.
+    pub fn kinetic_energy(&self) -> f64 {
+        (0..self.live.len())
+            .map(|slot| self.live.kinetic_energy(slot))
+            .sum()
+    }
+
+    /// The total gravitational potential energy of every pair, in joules.
+    /// This uses the same softening, cutoff distance, and minimum pulling mass as the forces do,
+    /// so it's what the integrator is actually trying to conserve.
+    pub fn potential_energy(&self) -> f64 {
+        let live = &self.live;
+        let config = &self.config;
+        let max_squared = config.max_pull_distance * config.max_pull_distance;
+        // Only pairs with at least one puller in them count, so there's no need to look at the rest.
+        // With lots of test particles around that's most of them.
+        let pullers: Vec<usize> = (0..live.len())
+            .filter(|&slot| gravity::pulls(config, live.mass[slot]))
+            .collect();
+        let mut energy = 0.0;
+        for j in 0..live.len() {
+            // Test particles don't weigh anything as far as gravity goes
+            if live.mass[j] == 0.0 {
+                continue;
+            }
+            let j_pulls = gravity::pulls(config, live.mass[j]);
+            for &i in &pullers {
+                // Pairs of pullers only get counted once
+                if j_pulls && i >= j {
+                    break;
+                }
+                let dist_squared = (live.pos[j] - live.pos[i]).square_length();
+                if dist_squared > max_squared || dist_squared == 0.0 {
+                    continue;
+                }
+                energy -= config.grav_constant
+                    * live.mass[i]
+                    * live.mass[j]
+                    * gravity::inverse_distance(config.softening, dist_squared);
+            }
+        }
+        energy
+    }
+
+    /// Kinetic plus potential energy, in joules.
+    pub fn total_energy(&self) -> f64 {
+        self.kinetic_energy() + self.potential_energy()
+    }
+
+    /// How far the total energy has wandered since the simulation was made,
+    /// as a fraction of what it started at.
+    /// Collisions and adding or changing orbiters change the energy too, so this is only
+    /// a measure of the integrator when none of those happen.
+    /// If it started at exactly zero there's nothing to divide by, so it's the drift in joules instead.
+    pub fn energy_drift(&self) -> f64 {
+        let drift = self.total_energy() - self.initial_energy;
+        if self.initial_energy == 0.0 {
+            drift
+        } else {
+            drift / self.initial_energy.abs()
+        }
+    }
+
+    /// The total linear momentum of everything, in kg m/s.
+    /// Immovable things don't move, so they don't count.
+    pub fn momentum(&self) -> Vector2D<f64> {
+        let live = &self.live;
+        (0..live.len()).fold(Vector2D::zero(), |momentum, slot| {
+            momentum + live.vel[slot] * live.mass[slot]
+        })
+    }
+
+    /// The total angular momentum of everything around (0, 0), in kg m^2/s.
+    /// Positive is counterclockwise.
+    pub fn angular_momentum(&self) -> f64 {
+        let live = &self.live;
+        (0..live.len())
+            .map(|slot| live.mass[slot] * live.pos[slot].to_vector().cross(live.vel[slot]))
+            .sum()
+    }
+
+    /// Where the center of mass of everything is.
+    pub fn center_of_mass(&self) -> Point2D<f64> {
+        let live = &self.live;
+        let total_mass: f64 = live.mass.iter().sum();
+        if total_mass == 0.0 {
+            return Point2D::zero();
+        }
+        let weighted = (0..live.len()).fold(Vector2D::zero(), |sum, slot| {
+            sum + live.pos[slot].to_vector() * live.mass[slot]
+        });
+        (weighted / total_mass).to_point()
+    }
+}
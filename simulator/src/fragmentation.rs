@@ -0,0 +1,188 @@
+//! Handles smashing orbiters into debris when they hit each other hard enough.
+
+use crate::bodies::{Body, Kinemat, Orbiter};
+use crate::collision::merge;
+use crate::config::{Fragmentation, SimulationConfig};
+use euclid::default::Vector2D;
+use rand::rngs::SmallRng;
+use rand::{Rng, SeedableRng};
+use std::f64::consts::PI;
+
+/// Work out what's left after a collision violent enough to break things apart.
+/// Returns `None` if it isn't violent enough, in which case the two should just merge.
+///
+/// Otherwise the first orbiter is the largest remnant and the rest are debris.
+/// Together they have exactly the mass, center of mass, and momentum of the two that collided.
+pub(crate) fn fragment(
+    config: &SimulationConfig,
+    fragmentation: &Fragmentation,
+    seed: u64,
+    (body, kmat): (&Body, &Kinemat),
+    (other_body, other_kmat): (&Body, &Kinemat),
+) -> Option<Vec<Orbiter>> {
+    if body.immovable || other_body.immovable {
+        // Nothing can hit an immovable thing hard enough to break it
+        return None;
+    }
+
+    let total_mass = body.mass + other_body.mass;
+    let reduced_mass = body.mass * other_body.mass / total_mass;
+    // Kinetic energy of the impact in the center of mass frame
+    let impact_energy = 0.5 * reduced_mass * (other_kmat.vel - kmat.vel).square_length();
+    let specific_energy = impact_energy / total_mass;
+    if specific_energy <= fragmentation.threshold {
+        return None;
+    }
+
+    // The harder the hit, the less is left in one piece
+    let remnant_mass = total_mass * fragmentation.threshold / specific_energy;
+    let debris_mass = total_mass - remnant_mass;
+    if debris_mass < fragmentation.min_fragment_mass {
+        return None;
+    }
+    let count = fragmentation
+        .max_fragments
+        .min((debris_mass / fragmentation.min_fragment_mass) as usize);
+    if count == 0 {
+        return None;
+    }
+
+    let mut rng = SmallRng::seed_from_u64(seed);
+    let masses = debris_masses(
+        &mut rng,
+        count,
+        debris_mass,
+        fragmentation.min_fragment_mass,
+        remnant_mass.min(debris_mass),
+        fragmentation.power_law_index,
+    );
+    // If the debris couldn't all fit in pieces no bigger than the remnant, the remnant keeps the rest
+    let debris_mass: f64 = masses.iter().sum();
+    let remnant_mass = total_mass - debris_mass;
+
+    // Everything keeps the same density as the two that collided
+    let volume_per_mass = (body.radius.powi(3) + other_body.radius.powi(3)) / total_mass;
+    let radius_of = |mass: f64| (mass * volume_per_mass).cbrt();
+    let remnant_radius = radius_of(remnant_mass);
+    let biggest_radius = radius_of(masses.iter().cloned().fold(0.0, f64::max));
+
+    // Put the debris on a ring around the remnant, far enough out that nothing overlaps.
+    // The directions get jittered by up to a quarter of the spacing each way,
+    // so neighbors can end up as little as half the spacing apart.
+    let closest_angle = PI / count as f64;
+    let ring_radius = (remnant_radius + 2.0 * biggest_radius)
+        .max(1.1 * biggest_radius / (closest_angle / 2.0).sin());
+    let offset: f64 = rng.gen_range(0.0, 2.0 * PI);
+    let directions: Vec<Vector2D<f64>> = (0..count)
+        .map(|k| {
+            let jitter: f64 = rng.gen_range(-0.25, 0.25);
+            let angle = offset + (k as f64 + jitter) * 2.0 * PI / count as f64;
+            Vector2D::new(angle.cos(), angle.sin())
+        })
+        .collect();
+
+    // The debris is placed relative to the remnant, so work out where the remnant
+    // has to be (and how fast it has to go) for the center of mass and momentum to stay put.
+    let lopsidedness = masses
+        .iter()
+        .zip(directions.iter())
+        .fold(Vector2D::zero(), |acc, (&mass, &dir)| acc + dir * mass)
+        / total_mass;
+
+    // Throw it out a little faster than escape velocity, if there's enough energy to do that.
+    // Whatever the impact didn't spend breaking things up is what it has to spend.
+    // The remnant recoils to keep the momentum the same, so in the center of mass frame
+    // everything together ends up with (debris_mass - total_mass * lopsidedness^2) * ejection_speed^2 / 2.
+    let escape_speed = (2.0 * config.grav_constant * remnant_mass / remnant_radius).sqrt();
+    let spare_energy = impact_energy - fragmentation.threshold * total_mass;
+    let moving_mass = debris_mass - total_mass * lopsidedness.square_length();
+    let ejection_speed = (1.1 * escape_speed).min((2.0 * spare_energy / moving_mass).sqrt());
+
+    let Orbiter(merged, merged_kmat) = merge(body, kmat, other_body, other_kmat);
+    let remnant_kmat = Kinemat::new(
+        merged_kmat.pos - lopsidedness * ring_radius,
+        merged_kmat.vel - lopsidedness * ejection_speed,
+    );
+
+    let mut orbiters = Vec::with_capacity(count + 1);
+    for (k, (&mass, &dir)) in masses.iter().zip(directions.iter()).enumerate() {
+        orbiters.push(Orbiter(
+            Body {
+                mass,
+                radius: radius_of(mass),
+                name: format!("{} debris {}", merged.name, k + 1),
+                ..merged.clone()
+            },
+            Kinemat::new(
+                remnant_kmat.pos + dir * ring_radius,
+                remnant_kmat.vel + dir * ejection_speed,
+            ),
+        ));
+    }
+    orbiters.insert(
+        0,
+        Orbiter(
+            Body {
+                mass: remnant_mass,
+                radius: remnant_radius,
+                ..merged
+            },
+            remnant_kmat,
+        ),
+    );
+    Some(orbiters)
+}
+
+/// Pick `count` debris masses from a power law between `min` and `max`, scaled so they add up to `total`.
+/// Scaling never pushes anything past `min` or `max`: whatever would go past one stays there instead,
+/// and the rest make up the difference. If `total` is more than `count` pieces of `max`, they add up to less.
+fn debris_masses(
+    rng: &mut SmallRng,
+    count: usize,
+    total: f64,
+    min: f64,
+    max: f64,
+    index: f64,
+) -> Vec<f64> {
+    let max = max.max(min);
+    // Invert the cumulative distribution of a power law cut off at both ends
+    let (low, high) = (min.powf(-index), max.powf(-index));
+    let mut masses: Vec<f64> = (0..count)
+        .map(|_| {
+            let u: f64 = rng.gen();
+            (low - u * (low - high)).powf(-index.recip())
+        })
+        .collect();
+    // Every pass scales the same way, either all up or all down, so nothing that stops ever needs to start again
+    let mut stopped = vec![false; count];
+    loop {
+        let sum = |stopped_too: bool| -> f64 {
+            masses
+                .iter()
+                .zip(stopped.iter())
+                .filter(|&(_, &stopped)| stopped == stopped_too)
+                .map(|(mass, _)| mass)
+                .sum()
+        };
+        let free = sum(false);
+        if free == 0.0 {
+            return masses;
+        }
+        let scale = (total - sum(true)) / free;
+        let mut done = true;
+        for (mass, stopped) in masses.iter_mut().zip(stopped.iter_mut()) {
+            if *stopped {
+                continue;
+            }
+            *mass *= scale;
+            if *mass < min || *mass > max {
+                *mass = mass.max(min).min(max);
+                *stopped = true;
+                done = false;
+            }
+        }
+        if done {
+            return masses;
+        }
+    }
+}
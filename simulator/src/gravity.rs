@@ -0,0 +1,163 @@
+//! Handles working out how hard everything pulls on everything else.
+
+use crate::barnes_hut::QuadTree;
+use crate::config::{SimulationConfig, Softening};
+use euclid::default::{Point2D, Vector2D};
+use serde::{Deserialize, Serialize};
+
+/// How to add up the pull of every orbiter.
+#[derive(Copy, Clone, Debug, PartialEq, Default, Serialize, Deserialize)]
+pub enum GravitySolver {
+    /// Add up every pair exactly. O(N^2).
+    #[default]
+    Direct,
+    /// Lump faraway groups of orbiters together using a quadtree. O(N log N).
+    /// `theta` is the opening angle: a group is lumped together if its width divided by
+    /// its distance is smaller than this. 0 is exact (and slow), 0.5 is a good default.
+    BarnesHut { theta: f64 },
+}
+
+/// Fill `acc` with the gravitational acceleration on each orbiter.
+/// Immovable orbiters still pull on everything else, but never accelerate themselves.
+/// Only the orbiters heavy enough to pull get looped over, so piles of test particles are cheap.
+pub fn accelerations(
+    solver: GravitySolver,
+    config: &SimulationConfig,
+    pos: &[Point2D<f64>],
+    masses: &[f64],
+    immovable: &[bool],
+    acc: &mut [Vector2D<f64>],
+) {
+    match solver {
+        GravitySolver::Direct => {
+            let pullers: Vec<usize> = (0..pos.len())
+                .filter(|&j| pulls(config, masses[j]))
+                .collect();
+            fill(acc, |i| {
+                if immovable[i] {
+                    return Vector2D::zero();
+                }
+                direct(config, i, pos, masses, &pullers)
+            })
+        }
+        GravitySolver::BarnesHut { theta } => {
+            let tree = QuadTree::new(config, pos, masses);
+            fill(acc, |i| {
+                if immovable[i] {
+                    return Vector2D::zero();
+                }
+                tree.acceleration(config, i, pos, masses, theta)
+            })
+        }
+    }
+}
+
+/// Set each acceleration to `f(index)`, spread over every core if the `parallel` feature is on.
+/// Each orbiter's pulls are always added up in the same order,
+/// so the results are bit-for-bit the same either way.
+fn fill<F>(acc: &mut [Vector2D<f64>], f: F)
+where
+    F: Fn(usize) -> Vector2D<f64> + Send + Sync,
+{
+    #[cfg(feature = "parallel")]
+    {
+        use rayon::prelude::*;
+        acc.par_iter_mut()
+            .enumerate()
+            .for_each(|(i, acc)| *acc = f(i));
+    }
+    #[cfg(not(feature = "parallel"))]
+    {
+        for (i, acc) in acc.iter_mut().enumerate() {
+            *acc = f(i);
+        }
+    }
+}
+
+/// Add up the pull of every orbiter in `pullers` besides `target` on orbiter `target`.
+fn direct(
+    config: &SimulationConfig,
+    target: usize,
+    pos: &[Point2D<f64>],
+    masses: &[f64],
+    pullers: &[usize],
+) -> Vector2D<f64> {
+    let mut acc = Vector2D::zero();
+    for &j in pullers {
+        if j == target {
+            continue;
+        }
+        acc += pull(config, pos[target], pos[j], masses[j]);
+    }
+    acc
+}
+
+/// Whether something this heavy is worth pulling on other things with
+pub(crate) fn pulls(config: &SimulationConfig, mass: f64) -> bool {
+    mass > config.min_pull_mass
+}
+
+/// The acceleration something at `at` feels from `mass` at `from`.
+pub(crate) fn pull(
+    config: &SimulationConfig,
+    at: Point2D<f64>,
+    from: Point2D<f64>,
+    mass: f64,
+) -> Vector2D<f64> {
+    let delta = from - at;
+    let dist_squared = delta.square_length();
+    if dist_squared > config.max_pull_distance * config.max_pull_distance || dist_squared == 0.0 {
+        return Vector2D::zero();
+    }
+    // a = G * m / r^2, pointing at the other orbiter
+    delta * (config.grav_constant * mass * inverse_cube(config.softening, dist_squared))
+}
+
+/// What 1 / r^3 becomes with softening, so that the acceleration is `delta * G * m * inverse_cube`.
+fn inverse_cube(softening: Softening, dist_squared: f64) -> f64 {
+    match softening {
+        Softening::None => 1.0 / (dist_squared * dist_squared.sqrt()),
+        Softening::Plummer { length } => {
+            let soft_squared = dist_squared + length * length;
+            1.0 / (soft_squared * soft_squared.sqrt())
+        }
+        Softening::Spline { length } => {
+            let dist = dist_squared.sqrt();
+            if dist >= length {
+                return 1.0 / (dist_squared * dist);
+            }
+            // Springel, Yoshida & White 2001, equation 6
+            let u = dist / length;
+            let kernel = if u < 0.5 {
+                32.0 / 3.0 + u * u * (32.0 * u - 38.4)
+            } else {
+                64.0 / 3.0 - 48.0 * u + 38.4 * u * u
+                    - 32.0 / 3.0 * u * u * u
+                    - 1.0 / 15.0 / (u * u * u)
+            };
+            kernel / (length * length * length)
+        }
+    }
+}
+
+/// What 1 / r becomes with softening, so that the potential energy of a pair is `-G * m1 * m2 * inverse_distance`.
+pub(crate) fn inverse_distance(softening: Softening, dist_squared: f64) -> f64 {
+    match softening {
+        Softening::None => 1.0 / dist_squared.sqrt(),
+        Softening::Plummer { length } => 1.0 / (dist_squared + length * length).sqrt(),
+        Softening::Spline { length } => {
+            let dist = dist_squared.sqrt();
+            if dist >= length {
+                return 1.0 / dist;
+            }
+            let u = dist / length;
+            let kernel = if u < 0.5 {
+                2.8 - u * u * (16.0 / 3.0 + u * u * (6.4 * u - 9.6))
+            } else {
+                3.2 - 1.0 / 15.0 / u
+                    - u * u * (32.0 / 3.0 + u * (-16.0 + u * (9.6 - 32.0 / 15.0 * u)))
+            };
+            kernel / length
+        }
+    }
+}
@@ -0,0 +1,184 @@
+//! Handles working out what orbits what.
+//!
+//! Bodies are sorted heaviest first, and each one goes down the tree from the top:
+//! into a body's Hill sphere if it's inside it and bound to it, as deep as it can go.
+//! The heaviest thing it's bound to at the top is its root; if it isn't bound to any, it's a root itself.
+
+use crate::bodies::Kinemat;
+use crate::{BodyId, SolarSystem};
+use std::collections::BTreeMap;
+
+/// What orbits what, at one moment. Like Sun -> Earth -> Moon.
+#[derive(Clone, Debug, Default)]
+pub struct Hierarchy {
+    nodes: BTreeMap<BodyId, Node>,
+    /// The things that don't orbit anything, heaviest first
+    roots: Vec<BodyId>,
+}
+
+#[derive(Clone, Debug)]
+struct Node {
+    primary: Option<BodyId>,
+    /// Heaviest first
+    satellites: Vec<BodyId>,
+    /// How far from it things count as orbiting it instead of its primary
+    hill_radius: f64,
+}
+
+/// Something that orbits something different than it used to.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct PrimaryChange {
+    pub id: BodyId,
+    /// What it used to orbit, if anything
+    pub old: Option<BodyId>,
+    /// What it orbits now, if anything
+    pub new: Option<BodyId>,
+}
+
+impl Hierarchy {
+    /// Work out what orbits what from the ID, mass, and kinemat of everything.
+    pub fn new<I>(bodies: I, grav_constant: f64) -> Self
+    where
+        I: IntoIterator<Item = (BodyId, f64, Kinemat)>,
+    {
+        let mut bodies: Vec<(BodyId, f64, Kinemat)> = bodies.into_iter().collect();
+        bodies.sort_by(|(a_id, a_mass, _), (b_id, b_mass, _)| {
+            b_mass.total_cmp(a_mass).then(a_id.cmp(b_id))
+        });
+        let lookup: BTreeMap<BodyId, (f64, Kinemat)> = bodies
+            .iter()
+            .map(|&(id, mass, kmat)| (id, (mass, kmat)))
+            .collect();
+        // Whether something at `kmat` with `mass` is bound to `primary`
+        let bound_to = |mass: f64, kmat: Kinemat, primary: BodyId| {
+            let (primary_mass, primary_kmat) = lookup[&primary];
+            let dist = (kmat.pos - primary_kmat.pos).length();
+            let speed = (kmat.vel - primary_kmat.vel).length();
+            speed * speed / 2.0 < grav_constant * (mass + primary_mass) / dist
+        };
+
+        let mut hierarchy = Hierarchy::default();
+        for &(id, mass, kmat) in bodies.iter() {
+            let root = hierarchy
+                .roots
+                .iter()
+                .copied()
+                .find(|&root| bound_to(mass, kmat, root));
+            let mut primary = root;
+            // Go as deep as possible
+            while let Some(current) = primary {
+                let deeper =
+                    hierarchy.nodes[&current]
+                        .satellites
+                        .iter()
+                        .copied()
+                        .find(|&satellite| {
+                            let dist = (kmat.pos - lookup[&satellite].1.pos).length();
+                            dist < hierarchy.nodes[&satellite].hill_radius
+                                && bound_to(mass, kmat, satellite)
+                        });
+                match deeper {
+                    Some(satellite) => primary = Some(satellite),
+                    None => break,
+                }
+            }
+
+            let hill_radius = match primary {
+                Some(primary) => {
+                    let (primary_mass, primary_kmat) = lookup[&primary];
+                    let dist = (kmat.pos - primary_kmat.pos).length();
+                    dist * (mass / (3.0 * (primary_mass + mass))).cbrt()
+                }
+                None => f64::INFINITY,
+            };
+            match primary {
+                Some(primary) => hierarchy
+                    .nodes
+                    .get_mut(&primary)
+                    // Anything it can be a satellite of is already in the tree
+                    .unwrap()
+                    .satellites
+                    .push(id),
+                None => hierarchy.roots.push(id),
+            }
+            hierarchy.nodes.insert(
+                id,
+                Node {
+                    primary,
+                    satellites: Vec::new(),
+                    hill_radius,
+                },
+            );
+        }
+        hierarchy
+    }
+
+    /// What something orbits, if it orbits anything
+    pub fn primary(&self, id: BodyId) -> Option<BodyId> {
+        self.nodes.get(&id)?.primary
+    }
+
+    /// Everything that orbits something directly, heaviest first
+    pub fn satellites(&self, id: BodyId) -> &[BodyId] {
+        self.nodes
+            .get(&id)
+            .map_or(&[], |node| node.satellites.as_slice())
+    }
+
+    /// Everything that doesn't orbit anything, heaviest first
+    pub fn roots(&self) -> &[BodyId] {
+        &self.roots
+    }
+
+    /// How far from something things count as orbiting it, in meters.
+    /// This is its Hill sphere around its primary, or infinite if it doesn't have one.
+    pub fn sphere_of_influence(&self, id: BodyId) -> Option<f64> {
+        Some(self.nodes.get(&id)?.hill_radius)
+    }
+
+    /// Everything in the tree, depth first, along with how deep it is. Roots are at depth 0.
+    pub fn walk(&self) -> Vec<(BodyId, usize)> {
+        let mut walked = Vec::with_capacity(self.nodes.len());
+        let mut stack: Vec<(BodyId, usize)> = self.roots.iter().rev().map(|&id| (id, 0)).collect();
+        while let Some((id, depth)) = stack.pop() {
+            walked.push((id, depth));
+            stack.extend(
+                self.satellites(id)
+                    .iter()
+                    .rev()
+                    .map(|&satellite| (satellite, depth + 1)),
+            );
+        }
+        walked
+    }
+
+    /// Everything in both hierarchies that orbits something different in this one than in `old`.
+    pub fn changes_since(&self, old: &Hierarchy) -> Vec<PrimaryChange> {
+        self.nodes
+            .iter()
+            .filter_map(|(&id, node)| {
+                let old = old.nodes.get(&id)?.primary;
+                if old == node.primary {
+                    None
+                } else {
+                    Some(PrimaryChange {
+                        id,
+                        old,
+                        new: node.primary,
+                    })
+                }
+            })
+            .collect()
+    }
+}
+
+impl SolarSystem {
+    /// Work out what orbits what right now.
+    pub fn get_hierarchy(&self) -> Hierarchy {
+        let live = &self.live;
+        Hierarchy::new(
+            (0..live.len()).map(|slot| (live.ids[slot], live.mass[slot], live.kinemat(slot))),
+            self.config.grav_constant,
+        )
+    }
+}
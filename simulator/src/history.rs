@@ -0,0 +1,403 @@
+//! Handles storing lots of saves in not much memory.
+//!
+//! Saves are grouped into segments. Each segment has one full-precision keyframe,
+//! and every save in it is stored as how far each orbiter has moved since the keyframe, in f32s.
+//! That's half the size, and since the deltas are all against the keyframe and not each other,
+//! any save can be thrown away without breaking the others.
+//!
+//! When the history gets too big, saves are thinned out so that they get further apart the older they are.
+//! Recent history stays detailed and old history stays around, just coarser.
+
+use crate::bodies::Kinemat;
+use crate::config::HistoryPolicy;
+use crate::registry::BodyId;
+use euclid::default::Vector2D;
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, VecDeque};
+use std::fmt;
+use std::mem::size_of;
+
+/// When one of the saves was made.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct SaveInfo {
+    /// Where it is in the history. 0 is the oldest.
+    pub index: usize,
+    /// Which step it was saved on
+    pub step: usize,
+    /// How many seconds into the simulation it was saved
+    pub time: f64,
+}
+
+/// Something that went wrong going through the history.
+#[derive(Clone, Debug, PartialEq)]
+pub enum HistoryError {
+    /// Asked for a save that isn't there
+    NoSuchSave { index: usize, count: usize },
+    /// Asked for a branch that isn't there
+    NoSuchBranch { index: usize, count: usize },
+    /// Asked for a time that the saves and now don't cover
+    OutsideHistory {
+        time: f64,
+        earliest: f64,
+        latest: f64,
+    },
+}
+
+impl fmt::Display for HistoryError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            HistoryError::NoSuchSave { index, count } => {
+                write!(f, "there's no save #{}, there are only {}", index, count)
+            }
+            HistoryError::NoSuchBranch { index, count } => {
+                write!(f, "there's no branch #{}, there are only {}", index, count)
+            }
+            HistoryError::OutsideHistory {
+                time,
+                earliest,
+                latest,
+            } => write!(
+                f,
+                "{}s isn't in the history, which goes from {}s to {}s",
+                time, earliest, latest
+            ),
+        }
+    }
+}
+
+impl std::error::Error for HistoryError {}
+
+/// One saved state, with everything at full size.
+#[derive(Clone)]
+pub(crate) struct Save {
+    pub frame: usize,
+    pub time: f64,
+    /// The ID and kinemat of everything that was alive, in the order they were simulated.
+    pub kinemats: Vec<(BodyId, Kinemat)>,
+}
+
+impl Save {
+    /// Guess where everything was at `time`, which is somewhere between this save and `other`.
+    /// This uses cubic Hermite interpolation, which matches where everything was and how fast it was going
+    /// at both saves, so things follow their orbits smoothly instead of cutting corners.
+    /// Things that are only in one of the saves are included if that save is the closer one.
+    pub fn interpolate(&self, other: &Save, time: f64) -> Vec<(BodyId, Kinemat)> {
+        let span = other.time - self.time;
+        if span == 0.0 {
+            return self.kinemats.clone();
+        }
+        let s = (time - self.time) / span;
+        let closer = if s < 0.5 { self } else { other };
+        let starts: HashMap<BodyId, Kinemat> = self.kinemats.iter().copied().collect();
+        let ends: HashMap<BodyId, Kinemat> = other.kinemats.iter().copied().collect();
+
+        // The Hermite basis functions, and how fast they change
+        let (s2, s3) = (s * s, s * s * s);
+        let (h00, h10, h01, h11) = (
+            2.0 * s3 - 3.0 * s2 + 1.0,
+            s3 - 2.0 * s2 + s,
+            -2.0 * s3 + 3.0 * s2,
+            s3 - s2,
+        );
+        let (d00, d10, d01, d11) = (
+            6.0 * s2 - 6.0 * s,
+            3.0 * s2 - 4.0 * s + 1.0,
+            -6.0 * s2 + 6.0 * s,
+            3.0 * s2 - 2.0 * s,
+        );
+        closer
+            .kinemats
+            .iter()
+            .map(|&(id, kmat)| match (starts.get(&id), ends.get(&id)) {
+                (Some(a), Some(b)) => {
+                    let pos = a.pos.to_vector() * h00
+                        + a.vel * (h10 * span)
+                        + b.pos.to_vector() * h01
+                        + b.vel * (h11 * span);
+                    let vel = (a.pos.to_vector() * d00 + b.pos.to_vector() * d01) / span
+                        + a.vel * d10
+                        + b.vel * d11;
+                    (id, Kinemat::new(pos.to_point(), vel))
+                }
+                _ => (id, kmat),
+            })
+            .collect()
+    }
+}
+
+#[derive(Clone, Default, Serialize, Deserialize)]
+pub(crate) struct History {
+    segments: VecDeque<Segment>,
+    /// Roughly how many bytes all of this takes up
+    bytes: usize,
+}
+
+#[derive(Clone, Serialize, Deserialize)]
+struct Segment {
+    key: Vec<(BodyId, Kinemat)>,
+    saves: Vec<CompactSave>,
+    /// How many saves were ever put in this segment, including ones that got thinned out since
+    pushed: usize,
+}
+
+#[derive(Clone, Serialize, Deserialize)]
+struct CompactSave {
+    frame: usize,
+    time: f64,
+    /// How this differs from the keyframe. `None` means it is the keyframe.
+    delta: Option<Delta>,
+}
+
+#[derive(Clone, Serialize, Deserialize)]
+struct Delta {
+    /// How far each thing in the keyframe moved (x, y) and sped up (vx, vy), or `None` if it's gone.
+    moved: Vec<Option<[f32; 4]>>,
+    /// Things that weren't in the keyframe at all
+    born: Vec<(BodyId, Kinemat)>,
+}
+
+impl History {
+    /// How many saves there are
+    pub fn len(&self) -> usize {
+        self.segments
+            .iter()
+            .map(|segment| segment.saves.len())
+            .sum()
+    }
+
+    /// Roughly how many bytes of memory the history is using
+    pub fn bytes(&self) -> usize {
+        self.bytes
+    }
+
+    /// Store a new save, then thin things out if there's too much.
+    pub fn push(&mut self, save: Save, policy: &HistoryPolicy) {
+        let new_segment = match self.segments.back() {
+            Some(segment) => segment.pushed >= policy.keyframe_every.max(1),
+            None => true,
+        };
+        if new_segment {
+            let segment = Segment {
+                key: save.kinemats,
+                saves: vec![CompactSave {
+                    frame: save.frame,
+                    time: save.time,
+                    delta: None,
+                }],
+                pushed: 1,
+            };
+            self.bytes += segment.key_bytes() + size_of::<CompactSave>();
+            self.segments.push_back(segment);
+        } else {
+            // Just checked there's at least one segment
+            let segment = self.segments.back_mut().unwrap();
+            let compact = CompactSave {
+                frame: save.frame,
+                time: save.time,
+                delta: Some(Delta::new(&segment.key, &save.kinemats)),
+            };
+            self.bytes += compact.bytes();
+            segment.saves.push(compact);
+            segment.pushed += 1;
+        }
+
+        while self.len() > 1
+            && (self.len() > policy.save_count || self.bytes > policy.memory_budget)
+        {
+            let index = self.thinnest();
+            self.remove(index);
+        }
+    }
+
+    /// When each save was made, oldest first
+    pub fn infos(&self) -> Vec<SaveInfo> {
+        self.segments
+            .iter()
+            .flat_map(|segment| segment.saves.iter())
+            .enumerate()
+            .map(|(index, save)| SaveInfo {
+                index,
+                step: save.frame,
+                time: save.time,
+            })
+            .collect()
+    }
+
+    /// The step the newest save was made on
+    pub fn newest_frame(&self) -> Option<usize> {
+        let segment = self.segments.back()?;
+        segment.saves.last().map(|save| save.frame)
+    }
+
+    /// How many seconds into the simulation the newest save is
+    pub fn newest_time(&self) -> Option<f64> {
+        let segment = self.segments.back()?;
+        segment.saves.last().map(|save| save.time)
+    }
+
+    /// Get a save back at full size.
+    pub fn get(&self, index: usize) -> Option<Save> {
+        let (segment, save) = self.locate(index)?;
+        let segment = &self.segments[segment];
+        let compact = &segment.saves[save];
+        let kinemats = match &compact.delta {
+            None => segment.key.clone(),
+            Some(delta) => delta.apply(&segment.key),
+        };
+        Some(Save {
+            frame: compact.frame,
+            time: compact.time,
+            kinemats,
+        })
+    }
+
+    /// Forget every save from `index` onwards.
+    pub fn truncate(&mut self, index: usize) {
+        while self.len() > index {
+            self.remove(self.len() - 1);
+        }
+    }
+
+    /// Every ID any save needs, possibly more than once.
+    pub fn ids(&self) -> impl Iterator<Item = BodyId> + '_ {
+        self.segments.iter().flat_map(|segment| {
+            let born = segment.saves.iter().flat_map(|save| {
+                save.delta
+                    .iter()
+                    .flat_map(|delta| delta.born.iter().map(|&(id, _)| id))
+            });
+            segment.key.iter().map(|&(id, _)| id).chain(born)
+        })
+    }
+
+    /// Which segment a save is in, and where it is in that segment
+    fn locate(&self, mut index: usize) -> Option<(usize, usize)> {
+        for (n, segment) in self.segments.iter().enumerate() {
+            if index < segment.saves.len() {
+                return Some((n, index));
+            }
+            index -= segment.saves.len();
+        }
+        None
+    }
+
+    fn frame(&self, index: usize) -> usize {
+        let (segment, save) = self.locate(index).unwrap();
+        self.segments[segment].saves[save].frame
+    }
+
+    /// Which save is the least missed if it's thrown away.
+    /// That's the one whose neighbors are closest together compared to how old it is,
+    /// so saves end up spaced further apart the older they get.
+    /// The newest save is never picked, and the oldest only goes once there's nothing left in between.
+    fn thinnest(&self) -> usize {
+        let len = self.len();
+        if len <= 2 {
+            return 0;
+        }
+        let frames: Vec<usize> = (0..len).map(|index| self.frame(index)).collect();
+        let newest = frames[len - 1];
+        (1..len - 1)
+            .min_by(|&a, &b| {
+                let crowding = |i: usize| {
+                    (frames[i + 1] - frames[i - 1]) as f64 / (newest - frames[i]).max(1) as f64
+                };
+                crowding(a).total_cmp(&crowding(b))
+            })
+            // There's at least one save between the oldest and newest
+            .unwrap()
+    }
+
+    fn remove(&mut self, index: usize) {
+        let (segment, save) = match self.locate(index) {
+            Some(location) => location,
+            None => return,
+        };
+        let removed = self.segments[segment].saves.remove(save);
+        self.bytes -= removed.bytes();
+        let segment_index = segment;
+        let segment = &mut self.segments[segment_index];
+        match segment.saves.as_mut_slice() {
+            [] => {
+                // Nothing needs the keyframe anymore
+                self.bytes -= segment.key_bytes();
+                self.segments.remove(segment_index);
+            }
+            [only] => {
+                // The keyframe's only there for this one save, so it might as well be the keyframe
+                let before = only.bytes();
+                if let Some(delta) = only.delta.take() {
+                    self.bytes -= before - only.bytes() + segment.key_bytes();
+                    segment.key = delta.apply(&segment.key);
+                    self.bytes += segment.key_bytes();
+                }
+            }
+            _ => {}
+        }
+    }
+}
+
+impl Segment {
+    fn key_bytes(&self) -> usize {
+        size_of::<Segment>() + self.key.len() * size_of::<(BodyId, Kinemat)>()
+    }
+}
+
+impl CompactSave {
+    fn bytes(&self) -> usize {
+        size_of::<CompactSave>()
+            + self.delta.as_ref().map_or(0, |delta| {
+                delta.moved.len() * size_of::<Option<[f32; 4]>>()
+                    + delta.born.len() * size_of::<(BodyId, Kinemat)>()
+            })
+    }
+}
+
+impl Delta {
+    /// Work out how `now` differs from `key`.
+    /// Both of them are in the order things were simulated, and that order never changes except
+    /// for things dying or being added at the end, so this can walk through them together.
+    fn new(key: &[(BodyId, Kinemat)], now: &[(BodyId, Kinemat)]) -> Self {
+        let mut moved = Vec::with_capacity(key.len());
+        let mut now_iter = now.iter().peekable();
+        for (id, key_kmat) in key.iter() {
+            match now_iter.peek() {
+                Some((now_id, now_kmat)) if now_id == id => {
+                    let dpos = now_kmat.pos - key_kmat.pos;
+                    let dvel = now_kmat.vel - key_kmat.vel;
+                    moved.push(Some([
+                        dpos.x as f32,
+                        dpos.y as f32,
+                        dvel.x as f32,
+                        dvel.y as f32,
+                    ]));
+                    now_iter.next();
+                }
+                _ => moved.push(None),
+            }
+        }
+        Delta {
+            moved,
+            born: now_iter.copied().collect(),
+        }
+    }
+
+    /// Rebuild the full save from the keyframe it's relative to.
+    fn apply(&self, key: &[(BodyId, Kinemat)]) -> Vec<(BodyId, Kinemat)> {
+        key.iter()
+            .zip(self.moved.iter())
+            .filter_map(|(&(id, kmat), moved)| {
+                moved.map(|[x, y, vx, vy]| {
+                    (
+                        id,
+                        Kinemat::new(
+                            kmat.pos + Vector2D::new(x as f64, y as f64),
+                            kmat.vel + Vector2D::new(vx as f64, vy as f64),
+                        ),
+                    )
+                })
+            })
+            .chain(self.born.iter().copied())
+            .collect()
+    }
+}
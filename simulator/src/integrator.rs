@@ -0,0 +1,195 @@
+//! Handles stepping orbiters forward through time.
+//!
+//! Gravity only depends on where things are, so every integrator here
+//! asks for accelerations given a set of positions.
+
+use euclid::default::{Point2D, Vector2D};
+
+/// Fills the second slice with the acceleration of each orbiter,
+/// given the positions of every orbiter in the first slice.
+pub type AccelerationFn<'a> = dyn FnMut(&[Point2D<f64>], &mut [Vector2D<f64>]) + 'a;
+
+/// Something that can move orbiters forward by one step.
+/// It has to be `Send` and `Sync` so a SolarSystem can go to another thread.
+pub trait Integrator: Send + Sync {
+    /// A short name for the integrator, suitable for showing to people
+    /// or for passing to `from_name`.
+    fn name(&self) -> &'static str;
+
+    /// Move every position and velocity forward by `dt` seconds.
+    /// `accel` can be called as many times as the integrator likes.
+    fn step(
+        &self,
+        pos: &mut [Point2D<f64>],
+        vel: &mut [Vector2D<f64>],
+        dt: f64,
+        accel: &mut AccelerationFn,
+    );
+}
+
+/// The names of all the built-in integrators, from cheapest to most accurate.
+pub const NAMES: &[&str] = &["euler", "leapfrog", "rk4", "yoshida4"];
+
+/// Get a built-in integrator from its name.
+pub fn from_name(name: &str) -> Option<Box<dyn Integrator>> {
+    match name {
+        "euler" => Some(Box::new(SemiImplicitEuler)),
+        "leapfrog" => Some(Box::new(Leapfrog)),
+        "rk4" => Some(Box::new(RungeKutta4)),
+        "yoshida4" => Some(Box::new(Yoshida4)),
+        _ => None,
+    }
+}
+
+/// Semi-implicit Euler: update the velocity, then move with the new velocity.
+/// This is what the simulator always used to do. It's cheap but drifts a lot.
+#[derive(Copy, Clone, Debug)]
+pub struct SemiImplicitEuler;
+
+impl Integrator for SemiImplicitEuler {
+    fn name(&self) -> &'static str {
+        "euler"
+    }
+
+    fn step(
+        &self,
+        pos: &mut [Point2D<f64>],
+        vel: &mut [Vector2D<f64>],
+        dt: f64,
+        accel: &mut AccelerationFn,
+    ) {
+        let mut acc = vec![Vector2D::zero(); pos.len()];
+        accel(pos, &mut acc);
+        kick(vel, &acc, dt);
+        drift(pos, vel, dt);
+    }
+}
+
+/// Leapfrog in kick-drift-kick form, AKA velocity Verlet.
+/// Second order and symplectic, so energy wobbles instead of drifting.
+#[derive(Copy, Clone, Debug)]
+pub struct Leapfrog;
+
+impl Integrator for Leapfrog {
+    fn name(&self) -> &'static str {
+        "leapfrog"
+    }
+
+    fn step(
+        &self,
+        pos: &mut [Point2D<f64>],
+        vel: &mut [Vector2D<f64>],
+        dt: f64,
+        accel: &mut AccelerationFn,
+    ) {
+        let mut acc = vec![Vector2D::zero(); pos.len()];
+        accel(pos, &mut acc);
+        kick(vel, &acc, dt / 2.0);
+        drift(pos, vel, dt);
+        accel(pos, &mut acc);
+        kick(vel, &acc, dt / 2.0);
+    }
+}
+
+/// Classic fourth-order Runge-Kutta.
+/// Very accurate over a single step but not symplectic, so it slowly bleeds energy.
+#[derive(Copy, Clone, Debug)]
+pub struct RungeKutta4;
+
+impl Integrator for RungeKutta4 {
+    fn name(&self) -> &'static str {
+        "rk4"
+    }
+
+    fn step(
+        &self,
+        pos: &mut [Point2D<f64>],
+        vel: &mut [Vector2D<f64>],
+        dt: f64,
+        accel: &mut AccelerationFn,
+    ) {
+        let len = pos.len();
+        // For x'' = a(x), the slope of the position at each stage is just the velocity there.
+        let stage = |base: &[Point2D<f64>], slope: &[Vector2D<f64>], h: f64| -> Vec<Point2D<f64>> {
+            base.iter()
+                .zip(slope.iter())
+                .map(|(&p, &s)| p + s * h)
+                .collect()
+        };
+        let stage_vel = |slope: &[Vector2D<f64>], h: f64| -> Vec<Vector2D<f64>> {
+            vel.iter()
+                .zip(slope.iter())
+                .map(|(&v, &s)| v + s * h)
+                .collect()
+        };
+
+        let vel1 = vel.to_vec();
+        let mut acc1 = vec![Vector2D::zero(); len];
+        accel(pos, &mut acc1);
+
+        let vel2 = stage_vel(&acc1, dt / 2.0);
+        let mut acc2 = vec![Vector2D::zero(); len];
+        accel(&stage(pos, &vel1, dt / 2.0), &mut acc2);
+
+        let vel3 = stage_vel(&acc2, dt / 2.0);
+        let mut acc3 = vec![Vector2D::zero(); len];
+        accel(&stage(pos, &vel2, dt / 2.0), &mut acc3);
+
+        let vel4 = stage_vel(&acc3, dt);
+        let mut acc4 = vec![Vector2D::zero(); len];
+        accel(&stage(pos, &vel3, dt), &mut acc4);
+
+        for i in 0..len {
+            pos[i] += (vel1[i] + vel2[i] * 2.0 + vel3[i] * 2.0 + vel4[i]) * (dt / 6.0);
+            vel[i] += (acc1[i] + acc2[i] * 2.0 + acc3[i] * 2.0 + acc4[i]) * (dt / 6.0);
+        }
+    }
+}
+
+/// Yoshida's fourth-order symplectic integrator.
+/// Three leapfrog-ish steps with magic coefficients, one of which goes backwards in time.
+#[derive(Copy, Clone, Debug)]
+pub struct Yoshida4;
+
+impl Integrator for Yoshida4 {
+    fn name(&self) -> &'static str {
+        "yoshida4"
+    }
+
+    fn step(
+        &self,
+        pos: &mut [Point2D<f64>],
+        vel: &mut [Vector2D<f64>],
+        dt: f64,
+        accel: &mut AccelerationFn,
+    ) {
+        let cbrt_2 = 2f64.cbrt();
+        let w1 = (2.0 - cbrt_2).recip();
+        let w0 = -cbrt_2 * w1;
+        let drifts = [w1 / 2.0, (w0 + w1) / 2.0, (w0 + w1) / 2.0, w1 / 2.0];
+        let kicks = [w1, w0, w1];
+
+        let mut acc = vec![Vector2D::zero(); pos.len()];
+        for (i, &d) in drifts.iter().enumerate() {
+            drift(pos, vel, d * dt);
+            if let Some(&k) = kicks.get(i) {
+                accel(pos, &mut acc);
+                kick(vel, &acc, k * dt);
+            }
+        }
+    }
+}
+
+/// Change every velocity by its acceleration over `dt`.
+fn kick(vel: &mut [Vector2D<f64>], acc: &[Vector2D<f64>], dt: f64) {
+    for (v, &a) in vel.iter_mut().zip(acc.iter()) {
+        *v += a * dt;
+    }
+}
+
+/// Move every position by its velocity over `dt`.
+fn drift(pos: &mut [Point2D<f64>], vel: &[Vector2D<f64>], dt: f64) {
+    for (p, &v) in pos.iter_mut().zip(vel.iter()) {
+        *p += v * dt;
+    }
+}
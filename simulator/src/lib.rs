@@ -1,303 +1,487 @@
 //! Handles the simulation of the solar system
 
+mod barnes_hut;
 pub mod bodies;
+mod branch;
+pub mod clock;
+mod collision;
+pub mod config;
+mod diagnostics;
+mod fragmentation;
+pub mod gravity;
+pub mod hierarchy;
+mod history;
+pub mod integrator;
+mod lineage;
+pub mod orbit;
+mod predict;
+mod rails;
+mod registry;
+mod snapshot;
+mod storage;
+use crate::barnes_hut::QuadTree;
 use crate::bodies::{Body, Kinemat, Orbiter};
-use euclid::default::Vector2D;
+use crate::branch::Branches;
+pub use crate::branch::{BodyDiff, BranchDiff, BranchInfo};
+use crate::clock::Epoch;
+use crate::config::SimulationConfig;
+use crate::gravity::GravitySolver;
+use crate::history::{History, Save};
+pub use crate::history::{HistoryError, SaveInfo};
+use crate::integrator::Integrator;
+use crate::lineage::Lineage;
+pub use crate::predict::Trajectory;
+pub use crate::rails::{Propagation, RailsError};
+use crate::registry::Registry;
+use crate::storage::Storage;
+pub use collision::{CollisionEvent, CollisionKind};
+pub use lineage::Ancestor;
+pub use registry::BodyId;
+pub use snapshot::{SnapshotError, SNAPSHOT_VERSION};
 
-use std::collections::{BTreeMap, HashMap, HashSet, VecDeque};
+use std::collections::{BTreeMap, HashSet};
+use std::f64::consts::PI;
+use std::sync::Arc;
 
 pub struct SolarSystem {
-    /// Maps each ID number to a Body.
-    /// When something is removed from `kinemats` it's not removed from here.
-    /// Hey isn't that a memory leak, I hear you ask? Shut up!
-    bodies: Vec<Body>,
-    /// Every single kinemat that needs to be processed.
-    kinemats: HashMap<usize, Kinemat>,
+    /// Maps each ID to a Body.
+    /// When something stops being simulated it stays in here until no save needs it anymore.
+    bodies: Registry,
+    /// Everything that's currently being simulated.
+    live: Storage,
     /// All the saved states.
-    /// This gets saved every `savePer` frames.
-    saves: VecDeque<HashMap<usize, Kinemat>>,
-    save_per: usize,
+    /// This gets saved every `save_every` frames.
+    history: History,
+    /// Every other timeline the simulation went down before going back to an old save
+    branches: Branches,
+    /// All the physical constants and policies
+    config: SimulationConfig,
     frames_elapsed: usize,
-
-    mode: SimulationMode,
-}
-
-/// What the solar system is up to.
-#[derive(Copy, Clone)]
-pub enum SimulationMode {
-    /// Normal simulation
-    Simulating,
-    /// Selecting a save to load (possibly)
-    LoadingSave(usize),
+    /// How many seconds have been simulated
+    time: f64,
+    /// Collisions that happened and haven't been drained yet
+    collision_events: Vec<CollisionEvent>,
+    /// What everything formed in a collision was made from
+    lineage: Lineage,
+    /// The total energy when the simulation was made, for working out the drift
+    initial_energy: f64,
+    /// How to step everything forward in time. Shared with any predictions.
+    integrator: Arc<dyn Integrator>,
+    /// How to add up everyone's gravity.
+    gravity_solver: GravitySolver,
+    /// Everything moving along a Kepler orbit instead of being integrated, and what it's going around
+    rails: BTreeMap<BodyId, BodyId>,
 }
 
 impl SolarSystem {
-    pub fn new(orbiters: Vec<Orbiter>) -> Self {
+    pub fn new(
+        orbiters: Vec<Orbiter>,
+        config: SimulationConfig,
+        integrator: Box<dyn Integrator>,
+    ) -> Self {
         let mut ss = SolarSystem {
-            save_per: SAVE_EVERY,
-            bodies: Vec::new(),
-            kinemats: HashMap::new(),
-            saves: VecDeque::new(),
+            config,
+            bodies: Registry::default(),
+            live: Storage::default(),
+            history: History::default(),
+            branches: Branches::default(),
             frames_elapsed: 0,
-            mode: SimulationMode::Simulating,
+            time: 0.0,
+            collision_events: Vec::new(),
+            lineage: Lineage::default(),
+            initial_energy: 0.0,
+            integrator: integrator.into(),
+            gravity_solver: GravitySolver::default(),
+            rails: BTreeMap::new(),
         };
         for oer in orbiters.into_iter() {
             ss.add_orbiter(oer);
         }
+        if let Some(mass) = ss.config.on_rails_below {
+            ss.put_on_rails_below(mass);
+        }
+        ss.initial_energy = ss.total_energy();
 
         ss
     }
 
     pub fn update(&mut self, dt: f64) {
-        match self.mode {
-            SimulationMode::Simulating => {
-                if self.frames_elapsed % self.save_per == 0 {
-                    // time to save!
-                    self.save()
-                }
+        if self
+            .frames_elapsed
+            .is_multiple_of(self.config.history.save_every)
+        {
+            // time to save!
+            self.save()
+        }
 
-                let mut forces: HashMap<usize, Vector2D<f64>> =
-                    HashMap::with_capacity(self.kinemats.len());
-                // Stores any new orbiters formed by collision, and the IDs of the two orbiters that formed it
-                let mut new_orbiters: Vec<(Orbiter, (usize, usize))> = Vec::new();
-                // IDs of things we need to skip for whatever reason, like it was combined with something else.
-                let mut skip_ids: HashSet<usize> = HashSet::new();
-
-                // Process both normal and smol kinemats
-                for (&id, kmat) in self.kinemats.iter() {
-                    if skip_ids.contains(&id) {
-                        continue;
-                    }
-                    let body = &self.bodies[id];
-
-                    // Only check to pull other kinemats if it's not small
-                    let debug_why_isnt_gravity_working = true;
-                    if body.mass > MIN_PULL_MASS || debug_why_isnt_gravity_working {
-                        // Hey, this is chonky enough to pull other stuff.
-                        for (&other_id, other_kmat) in self.kinemats.iter() {
-                            if other_id == id {
-                                continue;
-                            }
-
-                            let dx = other_kmat.pos.x - kmat.pos.x;
-                            let dy = other_kmat.pos.y - kmat.pos.y;
-                            let dist_squared = dx * dx + dy * dy;
-                            if dist_squared > MAX_PULL_DISTANCE * MAX_PULL_DISTANCE {
-                                continue;
-                            }
-
-                            let other_body = &self.bodies[other_id];
-                            if other_body.immovable {
-                                continue;
-                            }
-                            if dist_squared
-                                < (body.radius + other_body.radius)
-                                    * (body.radius + other_body.radius)
-                            {
-                                // ooh, a collision!
-                                skip_ids.insert(other_id);
-                                let combined = Orbiter(
-                                    Body {
-                                        mass: body.mass + other_body.mass,
-                                        // Combine the radii as if they were actually spheres instead of just adding them.
-                                        radius: (body.radius.powi(3) + other_body.radius.powi(3))
-                                            .cbrt(),
-                                        name: format!("{} & {}", body.name, other_body.name),
-                                        color: mix_colors(
-                                            body.color,
-                                            body.mass,
-                                            other_body.color,
-                                            other_body.mass,
-                                        ),
-                                        outline: mix_colors(
-                                            body.outline,
-                                            body.mass,
-                                            other_body.outline,
-                                            other_body.mass,
-                                        ),
-                                        immovable: body.immovable || other_body.immovable, // If either of them doesn't move, neither does this one
-                                    },
-                                    Kinemat::new(
-                                        // Pos at center of mass
-                                        (kmat.pos * body.mass
-                                            + (other_kmat.pos * other_body.mass).to_vector())
-                                            / (body.mass + other_body.mass),
-                                        // Momentum (mass * vel) is conserved!
-                                        if !body.immovable && !other_body.immovable {
-                                            (kmat.vel * body.mass
-                                                + other_kmat.vel * other_body.mass)
-                                                / (body.mass + other_body.mass)
-                                        } else {
-                                            Vector2D::zero()
-                                        },
-                                    ),
-                                );
-                                new_orbiters.push((combined, (id, other_id)));
-                            } else {
-                                // Actually calculate the force
-                                // it's negative because we're calculating the other body
-                                let force = -1.0
-                                    * GRAV_CONSTANT
-                                    * ((body.mass * other_body.mass) / dist_squared);
-                                let norm = Vector2D::new(dx, dy) / dist_squared.sqrt();
-                                let force = norm * force;
-                                forces.insert(
-                                    other_id,
-                                    force + *forces.get(&other_id).unwrap_or(&Vector2D::zero()),
-                                );
-                            }
-                        }
-                    }
-                }
+        self.step(dt);
+    }
 
-                for (new_orbiter, (id1, id2)) in new_orbiters.drain(0..) {
-                    // Stop processing the old kinemats
-                    self.kinemats.remove(&id1);
-                    self.kinemats.remove(&id2);
-                    // Add a shiny new orbiter!
-                    self.add_orbiter(new_orbiter);
-                }
-                for (&id, &force) in forces.iter() {
-                    if let Some(kmat) = self.kinemats.get_mut(&id) {
-                        let acc = force / self.bodies[id].mass;
-                        kmat.update(dt, acc);
-                    }
-                }
+    /// Everything `update` does besides saving.
+    fn step(&mut self, dt: f64) {
+        self.collide();
+        self.integrate(dt);
+
+        self.frames_elapsed += 1;
+        self.time += dt;
+    }
 
-                // dbg!(self.kinemats.get(&1).unwrap());
+    /// Simulate `duration` seconds in however many steps it takes to stay accurate.
+    /// Each step is at most `tolerance` times the shortest timescale in the system,
+    /// so close passes get lots of tiny steps and quiet stretches get a few big ones.
+    /// `tolerance` is relative; something like 0.01 is pretty accurate.
+    /// Returns the number of steps taken.
+    ///
+    /// Saves go by simulated time instead of steps: once every `save_every_seconds`,
+    /// so a close pass that takes thousands of tiny steps doesn't crowd everything else out of the history.
+    /// They only happen at the start of a call, so the closer `duration` is to dividing that evenly the better.
+    pub fn update_adaptive(&mut self, duration: f64, tolerance: f64) -> usize {
+        // Within half a call counts, so rounding errors in the time don't put every save off by a whole call
+        let due = self.history.newest_time().is_none_or(|time| {
+            (self.time - time).abs() + duration.abs() / 2.0
+                >= self.config.history.save_every_seconds
+        });
+        if due {
+            self.save();
+        }
+
+        // Don't let a near-collision grind everything to a halt
+        let min_step = duration.abs() / MAX_ADAPTIVE_STEPS as f64;
+        let mut remaining = duration.abs();
+        let mut steps = 0;
+        while remaining > 0.0 {
+            let dt = (tolerance * self.shortest_timescale())
+                .max(min_step)
+                .min(remaining);
+            self.step(dt.copysign(duration));
+            remaining -= dt;
+            steps += 1;
+        }
+        steps
+    }
 
-                self.frames_elapsed += 1;
+    /// The shortest time it takes anything to meaningfully change its orbit.
+    /// For each orbiter this is the smaller of how long it takes to cross the distance
+    /// to the nearest thing that pulls on it, and how long it takes to fall into it.
+    /// The nearest one is found with a quadtree, so this stays O(N log N) like Barnes-Hut.
+    fn shortest_timescale(&self) -> f64 {
+        self.shortest_timescale_of(&self.live)
+    }
+
+    /// `shortest_timescale` for everything in `live`, which doesn't have to be what's being simulated right now.
+    fn shortest_timescale_of(&self, live: &Storage) -> f64 {
+        let tree = QuadTree::new(&self.config, &live.pos, &live.mass);
+        let mut shortest = f64::INFINITY;
+        for i in 0..live.len() {
+            // Things on rails take exactly as long as they need to
+            let on_rails = self
+                .rails
+                .get(&live.ids[i])
+                .is_some_and(|&primary| live.slot(primary).is_some());
+            if live.immovable[i] || on_rails {
+                continue;
             }
-            SimulationMode::LoadingSave(_) => {
-                // Do jack shit
+            if let Some(j) = tree.nearest(i, &live.pos) {
+                let dist = (live.pos[j] - live.pos[i]).length();
+                let speed = (live.vel[j] - live.vel[i]).length();
+                let total_mass = live.mass[i] + live.mass[j];
+                let crossing_time = dist / speed;
+                let free_fall_time =
+                    (dist.powi(3) / (self.config.grav_constant * total_mass)).sqrt();
+                shortest = shortest.min(crossing_time).min(free_fall_time);
             }
         }
+        shortest
+    }
+
+    /// Move everything forward by `dt` using the current integrator,
+    /// then move everything on rails along its orbit.
+    fn integrate(&mut self, dt: f64) {
+        let rails = self.board_rails();
+        let mask = self.rails_mask(&rails);
+        let solver = self.gravity_solver;
+        let config = &self.config;
+        let Storage {
+            pos,
+            vel,
+            mass,
+            immovable,
+            ..
+        } = &mut self.live;
+        let skipped = mask.as_deref().unwrap_or(immovable);
+        // Immovable things have no velocity and get no acceleration, so they stay put.
+        // Things on rails get no acceleration either, and get put where they should be afterwards.
+        self.integrator.step(pos, vel, dt, &mut |pos, acc| {
+            gravity::accelerations(solver, config, pos, mass, skipped, acc)
+        });
+        self.ride_rails(&rails, dt);
+    }
+
+    /// Get how many seconds have been simulated
+    pub fn get_time(&self) -> f64 {
+        self.time
+    }
+
+    /// Get what date the simulation is at, if the config says when it started
+    pub fn get_date(&self) -> Option<Epoch> {
+        self.config.epoch.map(|epoch| epoch.after(self.time))
+    }
+
+    /// Take every collision that happened since the last time this was called, oldest first.
+    /// They pile up until something drains them, so call this regularly.
+    pub fn drain_collision_events(&mut self) -> impl Iterator<Item = CollisionEvent> + '_ {
+        self.collision_events.drain(..)
+    }
+
+    /// Get everything a body was made from, with what those were made from right after each of them.
+    /// Empty if it wasn't formed in a collision.
+    pub fn get_ancestors(&self, id: BodyId) -> Vec<Ancestor> {
+        self.lineage.ancestors(id)
+    }
+
+    /// Get the config the simulation is running with
+    pub fn get_config(&self) -> &SimulationConfig {
+        &self.config
+    }
+
+    /// Change the config. Takes effect on the next update.
+    pub fn set_config(&mut self, config: SimulationConfig) {
+        self.config = config;
+    }
+
+    /// Get how gravity is currently being added up
+    pub fn get_gravity_solver(&self) -> GravitySolver {
+        self.gravity_solver
+    }
+
+    /// Switch to a different way of adding up gravity. Takes effect on the next update.
+    pub fn set_gravity_solver(&mut self, solver: GravitySolver) {
+        self.gravity_solver = solver;
+    }
+
+    /// Get the name of the integrator currently in use
+    pub fn get_integrator_name(&self) -> &'static str {
+        self.integrator.name()
+    }
+
+    /// Switch to a different integrator. Takes effect on the next update.
+    pub fn set_integrator(&mut self, integrator: Box<dyn Integrator>) {
+        self.integrator = integrator.into();
     }
 
     /// Add an orbiter to the SolarSystem.
     /// Returns the ID it was given
-    pub fn add_orbiter(&mut self, oer: Orbiter) -> usize {
-        let id = self.bodies.len();
-        self.bodies.push(oer.0);
-        self.kinemats.insert(id, oer.1);
+    pub fn add_orbiter(&mut self, oer: Orbiter) -> BodyId {
+        let Orbiter(body, kmat) = oer;
+        let id = self.bodies.insert(body);
+        self.live.push(id, &self.bodies[id], kmat);
         id
     }
 
-    /// Get a BTreeMap associating each id with an Orbiter.
-    /// This makes a copy of the Oribters internally.
-    /// It gets converted to a BTreeMap so the State can get the next ID easily if there's holes
-    pub fn get_orbiters(&self) -> BTreeMap<usize, Orbiter> {
-        match self.mode {
-            SimulationMode::Simulating => &self.kinemats,
-            SimulationMode::LoadingSave(number) => &self.saves[number],
+    /// Change what something is, keeping where it is and how fast it's going.
+    /// Old saves and other branches still need it the way it was, so it gets a new ID, which is returned.
+    /// Anything on rails keeps going around it. Returns `None` if it doesn't exist right now.
+    pub fn set_body(&mut self, id: BodyId, body: Body) -> Option<BodyId> {
+        let kmat = self.live.kinemat(self.live.slot(id)?);
+        self.live.remove(&[id]);
+        let immovable = body.immovable;
+        let new_id = self.add_orbiter(Orbiter(body, kmat));
+        for primary in self.rails.values_mut() {
+            if *primary == id {
+                *primary = new_id;
+            }
         }
-        .iter()
-        .map(|(&id, &kmat)| (id, Orbiter(self.bodies[id].clone(), kmat)))
-        .collect()
+        if let Some(primary) = self.rails.remove(&id) {
+            if !immovable {
+                self.rails.insert(new_id, primary);
+            }
+        }
+        Some(new_id)
     }
 
-    /// Save the current state
-    fn save(&mut self) {
-        self.saves.push_back(self.kinemats.clone());
-        if self.saves.len() > SAVE_COUNT {
-            // too long! Void the oldest please.
-            self.saves.pop_front();
-        }
+    /// Get a BTreeMap associating each id with an Orbiter that's being simulated.
+    /// This makes a copy of the Oribters internally.
+    /// It gets converted to a BTreeMap so the State can get the next ID easily if there's holes
+    pub fn get_orbiters(&self) -> BTreeMap<BodyId, Orbiter> {
+        let to_orbiter =
+            |(id, kmat): (BodyId, Kinemat)| (id, Orbiter(self.bodies[id].clone(), kmat));
+        self.live.kinemats().map(to_orbiter).collect()
     }
 
-    /// Get the current mode
-    pub fn get_mode(&mut self) -> SimulationMode {
-        self.mode.clone()
+    /// Get a copy of one orbiter, if it exists right now.
+    pub fn get_orbiter(&self, id: BodyId) -> Option<Orbiter> {
+        let kmat = self.live.kinemat(self.live.slot(id)?);
+        Some(Orbiter(self.bodies[id].clone(), kmat))
     }
 
-    /// Turn on LoadingSave mode. Also saves the current state.
-    /// Returns whether it was successful or not.
-    pub fn enable_load(&mut self) {
-        println!(
-            "Backup size: {} using {}k bytes of ram",
-            self.saves.len(),
-            (self.saves.iter().fold(0, |mem, hmap| mem
-                + std::mem::size_of::<Kinemat>() * hmap.len()
-                + std::mem::size_of::<HashMap<usize, Kinemat>>())
-                + std::mem::size_of::<Vec<HashMap<usize, Kinemat>>>())
-                / 1024
+    /// Save the current state, on top of the saves that happen every `save_every` steps.
+    /// Does nothing if it was already saved this step.
+    pub fn save(&mut self) {
+        if self.history.newest_frame() == Some(self.frames_elapsed) {
+            return;
+        }
+        let count = self.history.len();
+        self.history.push(
+            Save {
+                frame: self.frames_elapsed,
+                time: self.time,
+                kinemats: self.live.kinemats().collect(),
+            },
+            &self.config.history,
         );
-        match self.mode {
-            SimulationMode::Simulating => {
-                self.save();
-                let newest_save = self.saves.len() - 1;
-                self.mode = SimulationMode::LoadingSave(newest_save);
-            }
-            SimulationMode::LoadingSave(_) => {
-                panic!("Tried to turn on loading when loading was already turned on!")
-            }
+        if self.history.len() <= count {
+            // Something got thinned out, so some bodies might not be needed anymore
+            self.forget_unused_bodies();
         }
     }
 
-    /// If in LoadingSave mode, request to change which backup is viewed.
-    /// Returns whether it was successful
-    pub fn change_load(&mut self, by: isize) {
-        match self.mode {
-            SimulationMode::LoadingSave(number) => {
-                let new_number = (number as isize + by)
-                    .max(0)
-                    .min(self.saves.len() as isize - 1) as usize;
-                if let Some(_) = self.saves.get(new_number) {
-                    // thats a valid index!
-                    self.mode = SimulationMode::LoadingSave(new_number);
-                } // else I don't know about that index somehow...
-            }
-            SimulationMode::Simulating => {
-                panic!("Tried to change which backup is loaded while in simulating mode!")
-            }
+    /// Drop every body that isn't alive and isn't in any save,
+    /// so its slot can be reused.
+    fn forget_unused_bodies(&mut self) {
+        let mut used: HashSet<BodyId> = self.live.ids.iter().copied().collect();
+        used.extend(self.history.ids());
+        used.extend(self.branches.ids());
+        let unused: Vec<BodyId> = self.bodies.ids().filter(|id| !used.contains(id)).collect();
+        for id in unused {
+            self.bodies.remove(id);
         }
     }
 
-    /// Exit LoadingSave mode, switch to the selected backup, and delete all things newer than the backup restored.
-    /// Returns whether it was successful yadayada etc
-    pub fn exit_load(&mut self) {
-        match self.mode {
-            SimulationMode::LoadingSave(number) => {
-                let save_to_restore = self.saves.get(number);
-                match save_to_restore {
-                    Some(restore) => {
-                        self.kinemats = restore.to_owned();
-                        // Erase all the bodies that don't exist anymore
-                        let mut i = 0usize;
-                        let rust_is_dumb = &self.kinemats;
-                        self.bodies.retain(|_| {
-                            let success = rust_is_dumb.contains_key(&i);
-                            i += 1;
-                            success
-                        });
-                        self.mode = SimulationMode::Simulating;
-                        self.saves.truncate(number);
+    /// When each save was made, oldest first.
+    pub fn list_saves(&self) -> Vec<SaveInfo> {
+        self.history.infos()
+    }
+
+    /// Roughly how many bytes of memory the saves are using
+    pub fn history_bytes(&self) -> usize {
+        self.history.bytes()
+    }
+
+    /// Get what everything looked like at a save, without going back to it.
+    pub fn preview_save(&self, index: usize) -> Result<BTreeMap<BodyId, Orbiter>, HistoryError> {
+        let save = self.history.get(index).ok_or(HistoryError::NoSuchSave {
+            index,
+            count: self.history.len(),
+        })?;
+        Ok(save
+            .kinemats
+            .into_iter()
+            .map(|(id, kmat)| (id, Orbiter(self.bodies[id].clone(), kmat)))
+            .collect())
+    }
+
+    /// Get what everything looked like `time` seconds into the simulation,
+    /// anywhere between the oldest save and now.
+    /// In between saves this is interpolated from the saves on either side, so it's only a guess,
+    /// and a worse one the further apart they are. Right on a save, it's whatever `preview_save` gives.
+    /// When the saves are far enough apart that something could get a good way around its orbit in between,
+    /// it's simulated again from the nearer save instead, which is slower but doesn't cut across orbits.
+    /// That isn't exact either: it takes evenly sized steps with whatever integrator is being used now,
+    /// which might not be how the simulation got from one save to the other.
+    pub fn state_at(&self, time: f64) -> Result<BTreeMap<BodyId, Orbiter>, HistoryError> {
+        let now = Save {
+            frame: self.frames_elapsed,
+            time: self.time,
+            kinemats: self.live.kinemats().collect(),
+        };
+        let mut times: Vec<f64> = self.history.infos().iter().map(|save| save.time).collect();
+        times.push(now.time);
+        // Time can run backwards, so the saves on either side could be either way round
+        let between = times
+            .windows(2)
+            .position(|pair| pair[0].min(pair[1]) <= time && time <= pair[0].max(pair[1]));
+        let kinemats = match between {
+            Some(index) => {
+                // Everything in `times` is either a save or now
+                let get = |index: usize| self.history.get(index).unwrap_or_else(|| now.clone());
+                let (before, after) = (get(index), get(index + 1));
+                let nearest = if (time - before.time).abs() <= (after.time - time).abs() {
+                    &before
+                } else {
+                    &after
+                };
+                let mut live = Storage::default();
+                for &(id, kmat) in nearest.kinemats.iter() {
+                    live.push(id, &self.bodies[id], kmat);
+                }
+                let span = after.time - before.time;
+                if span.abs()
+                    <= MAX_INTERPOLATED_ORBIT * 2.0 * PI * self.shortest_timescale_of(&live)
+                {
+                    before.interpolate(&after, time)
+                } else {
+                    // Too far apart to guess, so simulate it again from the nearer save.
+                    // Steps as long as they were on average in between are about as accurate as the first time,
+                    // though anything adaptive or with changing step sizes won't come out quite the same.
+                    let mut scratch = self.scratch_at(nearest);
+                    let dt = (span / (after.frame as f64 - before.frame as f64).max(1.0)).abs();
+                    let direction = time - scratch.time;
+                    let mut remaining = direction.abs();
+                    while remaining > 0.0 {
+                        let step = dt.min(remaining);
+                        scratch.step(step.copysign(direction));
+                        remaining -= step;
                     }
-                    None => panic!("Tried to restore to backup #{} but couldn't!", number),
+                    // Anything made in a collision along the way is only in the scratch copy's bodies
+                    return Ok(scratch.get_orbiters());
                 }
             }
-            SimulationMode::Simulating => {
-                panic!("Tried to exit loading mode while in simulating mode!")
+            None if time == now.time => now.kinemats,
+            None => {
+                return Err(HistoryError::OutsideHistory {
+                    time,
+                    earliest: times.iter().copied().fold(f64::INFINITY, f64::min),
+                    latest: times.iter().copied().fold(f64::NEG_INFINITY, f64::max),
+                })
             }
+        };
+        Ok(kinemats
+            .into_iter()
+            .map(|(id, kmat)| (id, Orbiter(self.bodies[id].clone(), kmat)))
+            .collect())
+    }
+
+    /// Go back to a save and keep simulating from there.
+    /// Saves that aren't keyframes only store how far things moved as f32s,
+    /// so going back to one is close but not bit for bit what the simulation had.
+    /// Unless it's where the simulation already is, this starts a new branch;
+    /// the one it came from is kept as it was, and can be switched back to with `switch_branch`.
+    pub fn restore_save(&mut self, index: usize) -> Result<(), HistoryError> {
+        let restore = self.history.get(index).ok_or(HistoryError::NoSuchSave {
+            index,
+            count: self.history.len(),
+        })?;
+        if index + 1 < self.history.len() || restore.frame != self.frames_elapsed {
+            self.branch_off(restore.frame, restore.time);
+        }
+        let mut live = Storage::default();
+        for &(id, kmat) in restore.kinemats.iter() {
+            live.push(id, &self.bodies[id], kmat);
         }
+        self.live = live;
+        self.frames_elapsed = restore.frame;
+        self.time = restore.time;
+        self.lineage.rewind(restore.frame);
+        self.collision_events.clear();
+        self.discard_saves_after(index)
     }
-}
 
-const SAVE_EVERY: usize = 1_000; // Save once every this many simulation steps
-const SAVE_COUNT: usize = 1_000; // Save this many previous points.
-const MIN_PULL_MASS: f64 = 1e23; // Any masses under this amount don't bother pulling on others (but do get pulled)
-const MAX_PULL_DISTANCE: f64 = 51e13; // Any masses farther than this amount away don't pull on each other. This is about 5x as far as Halley's comet is at the max.
+    /// Forget every save newer than `index`, without changing what's being simulated.
+    pub fn discard_saves_after(&mut self, index: usize) -> Result<(), HistoryError> {
+        if index >= self.history.len() {
+            return Err(HistoryError::NoSuchSave {
+                index,
+                count: self.history.len(),
+            });
+        }
+        self.history.truncate(index + 1);
+        // Anything born after the save we went back to never existed now
+        self.forget_unused_bodies();
+        Ok(())
+    }
+}
 
+const MAX_ADAPTIVE_STEPS: usize = 10_000; // update_adaptive never takes more than this many steps per call
+const MAX_INTERPOLATED_ORBIT: f64 = 0.05; // state_at only interpolates across saves closer together than this much of the shortest orbit
+/// The real gravitational constant. SimulationConfig uses this by default.
 pub const GRAV_CONSTANT: f64 = 6.674e-11;
-
-// Interpolate two colors with a weighted average of the masses
-fn mix_colors(c1: u32, w1: f64, c2: u32, w2: f64) -> u32 {
-    [0x0000ff, 0x00ff00, 0xff0000]
-        .iter()
-        .fold(0, |wip_color, mask| {
-            let comp1 = c1 & mask;
-            let comp2 = c2 & mask;
-            let color = ((comp1 as f64 * w1 + comp2 as f64 * w2) / (w1 + w2)) as u32;
-            wip_color + (color & mask)
-        })
-}
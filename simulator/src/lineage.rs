@@ -0,0 +1,88 @@
+//! Keeps track of what every merged body was made from.
+
+use crate::bodies::Body;
+use crate::registry::BodyId;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+
+/// One of the things a body was made from.
+#[derive(Clone, Debug)]
+pub struct Ancestor {
+    pub id: BodyId,
+    /// Its name
+    pub name: String,
+    /// How heavy it was when it merged
+    pub mass: f64,
+    /// How many merges back it is: 1 for the things that made up the body directly,
+    /// 2 for the things that made up those, and so on.
+    pub generation: usize,
+    /// Which step it merged into its child on
+    pub merged_step: usize,
+    /// How many seconds into the simulation it merged into its child
+    pub merged_time: f64,
+    /// Whether it was made from other things too
+    pub has_ancestors: bool,
+}
+
+/// A graph from each body formed in a collision to the bodies that formed it.
+/// Bodies that weren't formed in a collision aren't in here at all.
+#[derive(Clone, Default, Serialize, Deserialize)]
+pub(crate) struct Lineage {
+    origins: BTreeMap<BodyId, Origin>,
+}
+
+/// How one body was formed
+#[derive(Clone, Serialize, Deserialize)]
+struct Origin {
+    step: usize,
+    time: f64,
+    /// The IDs, names, and masses of what it was made from.
+    /// These get copied because the bodies themselves are forgotten eventually.
+    parents: Vec<(BodyId, String, f64)>,
+}
+
+impl Lineage {
+    /// Remember that `child` came from `parents`.
+    pub fn record(&mut self, child: BodyId, parents: &[(BodyId, &Body)], step: usize, time: f64) {
+        self.origins.insert(
+            child,
+            Origin {
+                step,
+                time,
+                parents: parents
+                    .iter()
+                    .map(|(id, body)| (*id, body.name.clone(), body.mass))
+                    .collect(),
+            },
+        );
+    }
+
+    /// Every ancestor of a body, depth first, so each ancestor is followed by its own ancestors.
+    pub fn ancestors(&self, id: BodyId) -> Vec<Ancestor> {
+        let mut ancestors = Vec::new();
+        self.add_ancestors(id, 1, &mut ancestors);
+        ancestors
+    }
+
+    fn add_ancestors(&self, id: BodyId, generation: usize, ancestors: &mut Vec<Ancestor>) {
+        if let Some(origin) = self.origins.get(&id) {
+            for (parent_id, name, mass) in origin.parents.iter() {
+                ancestors.push(Ancestor {
+                    id: *parent_id,
+                    name: name.clone(),
+                    mass: *mass,
+                    generation,
+                    merged_step: origin.step,
+                    merged_time: origin.time,
+                    has_ancestors: self.origins.contains_key(parent_id),
+                });
+                self.add_ancestors(*parent_id, generation + 1, ancestors);
+            }
+        }
+    }
+
+    /// Forget every collision that happened on or after `step`, because the simulation went back before it.
+    pub fn rewind(&mut self, step: usize) {
+        self.origins.retain(|_, origin| origin.step < step);
+    }
+}
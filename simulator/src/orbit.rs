@@ -0,0 +1,243 @@
+//! Handles describing orbits in human terms.
+//!
+//! Everything is in 2D, so there's no inclination or ascending node;
+//! instead an orbit either goes counterclockwise (like everything in our solar system) or clockwise.
+//! Angles are in radians, measured counterclockwise from the +x axis.
+
+use crate::bodies::Kinemat;
+use crate::{BodyId, SolarSystem};
+use euclid::default::Vector2D;
+use std::f64::consts::PI;
+
+/// The shape of an orbit around a primary, and where along it something is.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct OrbitalElements {
+    /// Half the longest width of the ellipse, in meters.
+    /// Negative for hyperbolic orbits, and infinite for exactly parabolic ones.
+    pub semi_major_axis: f64,
+    /// 0 is a circle, up to 1 is an ellipse, 1 is a parabola, and past 1 is a hyperbola.
+    pub eccentricity: f64,
+    /// How far it is from the primary when it's a quarter of the way around from periapsis, in meters.
+    /// Unlike the semi-major axis, this is finite for every kind of orbit, parabolas included.
+    pub semi_latus_rectum: f64,
+    /// Which way periapsis is from the primary.
+    /// Circular orbits don't have a periapsis, so this is 0 for them.
+    pub argument_of_periapsis: f64,
+    /// How far around the orbit it is from periapsis, in the direction it's going.
+    /// This is between 0 and 2pi for orbits that come back around, and between -pi and pi for ones that don't,
+    /// so it's negative on the way in.
+    pub true_anomaly: f64,
+    /// How far around the orbit it would be if it went at a constant speed.
+    /// This goes up at a constant rate, so it's what to use to work out where something will be.
+    /// For hyperbolic orbits this is the hyperbolic mean anomaly, which isn't an angle at all,
+    /// and for parabolic ones it's half of Barker's `tan(v/2) + tan(v/2)^3 / 3`.
+    pub mean_anomaly: f64,
+    /// Whether it goes around clockwise
+    pub clockwise: bool,
+    /// G times the mass of both things, in m^3/s^2
+    pub gravitational_parameter: f64,
+}
+
+impl OrbitalElements {
+    /// Work out the orbit of something at `kmat` around a primary at `primary`,
+    /// where `gravitational_parameter` is G times the mass of both of them.
+    pub fn from_kinemat(kmat: Kinemat, primary: Kinemat, gravitational_parameter: f64) -> Self {
+        let mu = gravitational_parameter;
+        let pos = kmat.pos - primary.pos;
+        let vel = kmat.vel - primary.vel;
+        let dist = pos.length();
+        let angular_momentum = pos.cross(vel);
+
+        let energy = vel.square_length() / 2.0 - mu / dist;
+        let semi_major_axis = -mu / (2.0 * energy);
+        // Points at periapsis, and is as long as the eccentricity
+        let ecc_vector = (pos * (vel.square_length() - mu / dist) - vel * pos.dot(vel)) / mu;
+        let eccentricity = ecc_vector.length();
+
+        let argument_of_periapsis = if eccentricity < CIRCULAR {
+            0.0
+        } else {
+            ecc_vector.y.atan2(ecc_vector.x)
+        };
+        let clockwise = angular_momentum < 0.0;
+        let angle_from_periapsis = pos.y.atan2(pos.x) - argument_of_periapsis;
+        let true_anomaly = normalize(if clockwise {
+            -angle_from_periapsis
+        } else {
+            angle_from_periapsis
+        });
+        let true_anomaly = if eccentricity < 1.0 || true_anomaly <= PI {
+            true_anomaly
+        } else {
+            true_anomaly - 2.0 * PI
+        };
+
+        OrbitalElements {
+            semi_major_axis,
+            eccentricity,
+            semi_latus_rectum: angular_momentum * angular_momentum / mu,
+            argument_of_periapsis: normalize(argument_of_periapsis),
+            true_anomaly,
+            mean_anomaly: mean_from_true(true_anomaly, eccentricity),
+            clockwise,
+            gravitational_parameter,
+        }
+    }
+
+    /// Where something on this orbit is, given where the primary is.
+    /// This goes off the true anomaly; the mean anomaly is ignored.
+    pub fn to_kinemat(&self, primary: Kinemat) -> Kinemat {
+        let e = self.eccentricity;
+        let nu = self.true_anomaly;
+        let p = self.semi_latus_rectum;
+        let dist = p / (1.0 + e * nu.cos());
+        let speed = (self.gravitational_parameter / p).sqrt();
+
+        // With periapsis along +x and going counterclockwise
+        let pos = Vector2D::new(dist * nu.cos(), dist * nu.sin());
+        let vel = Vector2D::new(-speed * nu.sin(), speed * (e + nu.cos()));
+        let (pos, vel) = if self.clockwise {
+            (Vector2D::new(pos.x, -pos.y), Vector2D::new(vel.x, -vel.y))
+        } else {
+            (pos, vel)
+        };
+        let (sin, cos) = self.argument_of_periapsis.sin_cos();
+        let rotate = |v: Vector2D<f64>| Vector2D::new(v.x * cos - v.y * sin, v.x * sin + v.y * cos);
+
+        Kinemat::new(primary.pos + rotate(pos), primary.vel + rotate(vel))
+    }
+
+    /// Where it'll be on the same orbit `dt` seconds later, assuming nothing else pulls on it.
+    pub fn advanced(&self, dt: f64) -> Self {
+        let e = self.eccentricity;
+        let mean_anomaly = self.mean_anomaly + self.mean_motion() * dt;
+        let mean_anomaly = if self.is_bound() {
+            normalize(mean_anomaly)
+        } else {
+            mean_anomaly
+        };
+        OrbitalElements {
+            mean_anomaly,
+            true_anomaly: true_from_mean(mean_anomaly, e),
+            ..*self
+        }
+    }
+
+    /// Whether it'll keep coming back around
+    pub fn is_bound(&self) -> bool {
+        self.eccentricity < 1.0
+    }
+
+    /// How long it takes to go around once, in seconds, if it ever comes back
+    pub fn period(&self) -> Option<f64> {
+        if self.is_bound() {
+            Some(2.0 * PI / self.mean_motion())
+        } else {
+            None
+        }
+    }
+
+    /// How fast the mean anomaly goes up, in radians per second.
+    /// Parabolic orbits don't have a semi-major axis to go off, so theirs uses the semi-latus rectum.
+    pub fn mean_motion(&self) -> f64 {
+        let size = if self.eccentricity == 1.0 {
+            self.semi_latus_rectum
+        } else {
+            self.semi_major_axis.abs()
+        };
+        (self.gravitational_parameter / size.powi(3)).sqrt()
+    }
+
+    /// The closest it gets to the primary, in meters
+    pub fn periapsis(&self) -> f64 {
+        self.semi_latus_rectum / (1.0 + self.eccentricity)
+    }
+
+    /// The furthest it gets from the primary, in meters, if it ever turns around
+    pub fn apoapsis(&self) -> Option<f64> {
+        if self.is_bound() {
+            Some(self.semi_latus_rectum / (1.0 - self.eccentricity))
+        } else {
+            None
+        }
+    }
+}
+
+impl SolarSystem {
+    /// Work out the orbit of one orbiter around another, if they both exist right now.
+    pub fn get_orbital_elements(&self, id: BodyId, primary: BodyId) -> Option<OrbitalElements> {
+        let slot = self.live.slot(id)?;
+        let primary_slot = self.live.slot(primary)?;
+        let mu = self.config.grav_constant * (self.live.mass[slot] + self.live.mass[primary_slot]);
+        Some(OrbitalElements::from_kinemat(
+            self.live.kinemat(slot),
+            self.live.kinemat(primary_slot),
+            mu,
+        ))
+    }
+}
+
+/// Eccentricities under this count as circles, where periapsis could be anywhere
+const CIRCULAR: f64 = 1e-10;
+/// Newton's method on Kepler's equation usually takes a handful of steps; this is plenty
+const KEPLER_ITERATIONS: usize = 50;
+const KEPLER_TOLERANCE: f64 = 1e-15;
+
+/// Wrap an angle into 0..2pi
+fn normalize(angle: f64) -> f64 {
+    angle.rem_euclid(2.0 * PI)
+}
+
+fn mean_from_true(true_anomaly: f64, eccentricity: f64) -> f64 {
+    let e = eccentricity;
+    let (sin, cos) = true_anomaly.sin_cos();
+    if e < 1.0 {
+        let eccentric_anomaly = ((1.0 - e * e).sqrt() * sin).atan2(e + cos);
+        normalize(eccentric_anomaly - e * eccentric_anomaly.sin())
+    } else if e > 1.0 {
+        let hyperbolic_anomaly =
+            2.0 * (((e - 1.0) / (e + 1.0)).sqrt() * (true_anomaly / 2.0).tan()).atanh();
+        e * hyperbolic_anomaly.sinh() - hyperbolic_anomaly
+    } else {
+        // Barker's equation, halved so it goes up at the parabolic mean motion
+        let d = (true_anomaly / 2.0).tan();
+        (d + d * d * d / 3.0) / 2.0
+    }
+}
+
+/// Solve Kepler's equation to find how far around the orbit something actually is.
+fn true_from_mean(mean_anomaly: f64, eccentricity: f64) -> f64 {
+    let m = mean_anomaly;
+    let e = eccentricity;
+    if e < 1.0 {
+        // M = E - e sin E, by Newton's method. Starting at pi is safe for very eccentric orbits
+        let mut eccentric_anomaly = if e < 0.8 { m } else { PI };
+        for _ in 0..KEPLER_ITERATIONS {
+            let step = (eccentric_anomaly - e * eccentric_anomaly.sin() - m)
+                / (1.0 - e * eccentric_anomaly.cos());
+            eccentric_anomaly -= step;
+            if step.abs() < KEPLER_TOLERANCE {
+                break;
+            }
+        }
+        let half = eccentric_anomaly / 2.0;
+        normalize(2.0 * ((1.0 + e).sqrt() * half.sin()).atan2((1.0 - e).sqrt() * half.cos()))
+    } else if e > 1.0 {
+        // M = e sinh F - F
+        let mut hyperbolic_anomaly = (m / e).asinh();
+        for _ in 0..KEPLER_ITERATIONS {
+            let step = (e * hyperbolic_anomaly.sinh() - hyperbolic_anomaly - m)
+                / (e * hyperbolic_anomaly.cosh() - 1.0);
+            hyperbolic_anomaly -= step;
+            if step.abs() < KEPLER_TOLERANCE * hyperbolic_anomaly.abs().max(1.0) {
+                break;
+            }
+        }
+        2.0 * (((e + 1.0) / (e - 1.0)).sqrt() * (hyperbolic_anomaly / 2.0).tanh()).atan()
+    } else {
+        // Barker's equation has a closed form
+        let w = 3.0 * m;
+        let y = (w + (w * w + 1.0).sqrt()).cbrt();
+        2.0 * (y - 1.0 / y).atan()
+    }
+}
@@ -0,0 +1,125 @@
+//! Handles working out where things are going without actually simulating them there.
+//!
+//! A prediction runs a scratch copy of the simulation forward, so it goes exactly where `update` would,
+//! collisions and all, but nothing it does touches the real simulation or its saves.
+//! `state_at` uses scratch copies too, to go back over stretches between saves that are too long to interpolate.
+
+use crate::bodies::Kinemat;
+use crate::branch::Branches;
+use crate::history::{History, Save};
+use crate::lineage::Lineage;
+use crate::storage::Storage;
+use crate::{BodyId, SolarSystem};
+use std::collections::BTreeMap;
+use std::sync::Arc;
+
+/// Where something is going to go.
+#[derive(Clone, Debug, Default)]
+pub struct Trajectory {
+    /// How many seconds into the simulation each sample is, and where it is then
+    pub samples: Vec<(f64, Kinemat)>,
+    /// When it stops existing, if it hits something before the prediction ends
+    pub ended: Option<f64>,
+}
+
+impl SolarSystem {
+    /// Work out where things will go over the next `duration` seconds, taking steps of `dt` seconds,
+    /// without changing anything. A negative `duration` predicts backwards.
+    ///
+    /// Each trajectory starts with where it is right now, then gets a sample every `sample_every` steps and at the very end.
+    /// With `ids`, only those get trajectories (if they exist right now);
+    /// otherwise everything does, including anything made in collisions along the way.
+    pub fn predict(
+        &self,
+        duration: f64,
+        dt: f64,
+        sample_every: usize,
+        ids: Option<&[BodyId]>,
+    ) -> BTreeMap<BodyId, Trajectory> {
+        let mut scratch = self.scratch();
+        let wanted = |id: BodyId| ids.is_none_or(|ids| ids.contains(&id));
+        let mut trajectories: BTreeMap<BodyId, Trajectory> = BTreeMap::new();
+        let sample = |scratch: &SolarSystem, trajectories: &mut BTreeMap<BodyId, Trajectory>| {
+            for (id, kmat) in scratch.live.kinemats() {
+                if let Some(trajectory) = trajectories.get_mut(&id) {
+                    trajectory.samples.push((scratch.time, kmat));
+                }
+            }
+        };
+        for (id, _) in scratch.live.kinemats().filter(|&(id, _)| wanted(id)) {
+            trajectories.insert(id, Trajectory::default());
+        }
+        sample(&scratch, &mut trajectories);
+
+        let dt = dt.abs();
+        let sample_every = sample_every.max(1);
+        let mut remaining = duration.abs();
+        let mut steps = 0;
+        while remaining > 0.0 && dt > 0.0 {
+            let step = dt.min(remaining);
+            scratch.step(step.copysign(duration));
+            remaining -= step;
+            steps += 1;
+            let sampling = steps % sample_every == 0 || remaining <= 0.0;
+
+            for event in scratch.collision_events.drain(..) {
+                for id in [event.bodies.0, event.bodies.1].iter() {
+                    if scratch.live.slot(*id).is_none() {
+                        if let Some(trajectory) = trajectories.get_mut(id) {
+                            trajectory.ended = Some(event.time);
+                        }
+                    }
+                }
+                if ids.is_none() {
+                    for &id in event.results.iter() {
+                        // Anything new starts off where it ended up after this step
+                        if let (None, Some(slot)) = (trajectories.get(&id), scratch.live.slot(id)) {
+                            let mut trajectory = Trajectory::default();
+                            if !sampling {
+                                trajectory
+                                    .samples
+                                    .push((scratch.time, scratch.live.kinemat(slot)));
+                            }
+                            trajectories.insert(id, trajectory);
+                        }
+                    }
+                }
+            }
+            if sampling {
+                sample(&scratch, &mut trajectories);
+            }
+        }
+        trajectories
+    }
+
+    /// A scratch copy that starts off at `save` instead of where the simulation is now.
+    pub(crate) fn scratch_at(&self, save: &Save) -> SolarSystem {
+        let mut scratch = self.scratch();
+        scratch.live = Storage::default();
+        for &(id, kmat) in save.kinemats.iter() {
+            scratch.live.push(id, &self.bodies[id], kmat);
+        }
+        scratch.frames_elapsed = save.frame;
+        scratch.time = save.time;
+        scratch
+    }
+
+    /// A copy of everything needed to keep simulating, with no saves, branches, or lineage.
+    fn scratch(&self) -> SolarSystem {
+        SolarSystem {
+            bodies: self.bodies.clone(),
+            live: self.live.clone(),
+            history: History::default(),
+            branches: Branches::default(),
+            config: self.config.clone(),
+            frames_elapsed: self.frames_elapsed,
+            time: self.time,
+            collision_events: Vec::new(),
+            lineage: Lineage::default(),
+            initial_energy: self.initial_energy,
+            integrator: Arc::clone(&self.integrator),
+            gravity_solver: self.gravity_solver,
+            rails: self.rails.clone(),
+        }
+    }
+}
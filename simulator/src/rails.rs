@@ -0,0 +1,196 @@
+//! Handles moving things along fixed Kepler orbits instead of integrating them.
+//!
+//! Something on rails goes around its primary as if nothing else existed.
+//! Every step, its orbit is worked out from where it is relative to its primary,
+//! moved along by solving Kepler's equation, and put back relative to wherever the primary ended up.
+//! It still pulls on everything else, and still collides.
+//! If it or its primary stops existing, it goes back to being integrated,
+//! at least until going back to a save where they both still exist.
+
+use crate::bodies::Kinemat;
+use crate::orbit::OrbitalElements;
+use crate::{BodyId, SolarSystem};
+use std::fmt;
+
+/// How something gets moved along.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum Propagation {
+    /// By the integrator, feeling everything's gravity
+    Integrated,
+    /// Along a Kepler orbit around `primary`, ignoring everything else's gravity
+    OnRails { primary: BodyId },
+}
+
+/// Something that went wrong putting something on rails.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum RailsError {
+    /// It doesn't exist right now
+    NoSuchBody(BodyId),
+    /// It's immovable, so it can't go anywhere, rails or not
+    Immovable(BodyId),
+    /// It'd end up going around itself, maybe through other things on rails
+    Loop { id: BodyId, primary: BodyId },
+}
+
+impl fmt::Display for RailsError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            RailsError::NoSuchBody(id) => write!(f, "there's no body {}", id),
+            RailsError::Immovable(id) => write!(f, "{} is immovable", id),
+            RailsError::Loop { id, primary } => {
+                write!(
+                    f,
+                    "{} can't go around {}, which goes around it",
+                    id, primary
+                )
+            }
+        }
+    }
+}
+
+impl std::error::Error for RailsError {}
+
+/// Something on rails, partway through a step
+pub(crate) struct Rail {
+    slot: usize,
+    primary_slot: usize,
+    /// Its orbit before the step
+    elements: OrbitalElements,
+}
+
+impl SolarSystem {
+    /// How something is being moved along, if it exists right now.
+    pub fn get_propagation(&self, id: BodyId) -> Option<Propagation> {
+        self.live.slot(id)?;
+        Some(match self.primary_on_rails(id) {
+            Some(primary) => Propagation::OnRails { primary },
+            None => Propagation::Integrated,
+        })
+    }
+
+    /// What something on rails is going around, as long as that exists right now.
+    pub(crate) fn primary_on_rails(&self, id: BodyId) -> Option<BodyId> {
+        self.rails
+            .get(&id)
+            .copied()
+            .filter(|&primary| self.live.slot(primary).is_some())
+    }
+
+    /// Change how something gets moved along. Takes effect on the next update.
+    pub fn set_propagation(
+        &mut self,
+        id: BodyId,
+        propagation: Propagation,
+    ) -> Result<(), RailsError> {
+        let slot = self.live.slot(id).ok_or(RailsError::NoSuchBody(id))?;
+        match propagation {
+            Propagation::Integrated => {
+                self.rails.remove(&id);
+            }
+            Propagation::OnRails { primary } => {
+                if self.live.immovable[slot] {
+                    return Err(RailsError::Immovable(id));
+                }
+                self.live
+                    .slot(primary)
+                    .ok_or(RailsError::NoSuchBody(primary))?;
+                // Follow the primary's primary and so on, to make sure it doesn't come back around
+                let mut up = Some(primary);
+                while let Some(above) = up {
+                    if above == id {
+                        return Err(RailsError::Loop { id, primary });
+                    }
+                    up = self.rails.get(&above).copied();
+                }
+                self.rails.insert(id, primary);
+            }
+        }
+        Ok(())
+    }
+
+    /// Put everything lighter than `mass` on rails around whatever it orbits right now, going by `get_hierarchy`.
+    /// Things that don't orbit anything, and immovable things, stay integrated.
+    /// Returns how many things were put on rails.
+    pub fn put_on_rails_below(&mut self, mass: f64) -> usize {
+        let hierarchy = self.get_hierarchy();
+        let mut count = 0;
+        for slot in 0..self.live.len() {
+            let id = self.live.ids[slot];
+            if self.live.mass[slot] >= mass || self.live.immovable[slot] {
+                continue;
+            }
+            if let Some(primary) = hierarchy.primary(id) {
+                // The hierarchy is a tree and primaries are always heavier, so this can't loop
+                self.rails.insert(id, primary);
+                count += 1;
+            }
+        }
+        count
+    }
+
+    /// Work out the orbit of everything on rails before a step, primaries first,
+    /// skipping anything that or whose primary doesn't exist right now.
+    /// They stay in `rails`, since going back to a save can bring them back.
+    pub(crate) fn board_rails(&self) -> Vec<Rail> {
+        let live = &self.live;
+        // How many primaries on rails there are above something
+        let depth = |mut id: BodyId| {
+            let mut depth = 0;
+            while let Some(primary) = self.rails.get(&id) {
+                depth += 1;
+                id = *primary;
+            }
+            depth
+        };
+        let mut rails: Vec<(usize, BodyId, BodyId)> = self
+            .rails
+            .iter()
+            .filter(|(&id, &primary)| live.slot(id).is_some() && live.slot(primary).is_some())
+            .map(|(&id, &primary)| (depth(id), id, primary))
+            .collect();
+        rails.sort_by_key(|&(depth, id, _)| (depth, id));
+
+        rails
+            .into_iter()
+            .map(|(_, id, primary)| {
+                // Both exist, it was just checked
+                let slot = live.slot(id).unwrap();
+                let primary_slot = live.slot(primary).unwrap();
+                let mu = self.config.grav_constant * (live.mass[slot] + live.mass[primary_slot]);
+                Rail {
+                    slot,
+                    primary_slot,
+                    elements: OrbitalElements::from_kinemat(
+                        live.kinemat(slot),
+                        live.kinemat(primary_slot),
+                        mu,
+                    ),
+                }
+            })
+            .collect()
+    }
+
+    /// Whether each slot should be skipped by the integrator: either it's immovable or it's on rails.
+    /// `None` if that's just the immovable ones.
+    pub(crate) fn rails_mask(&self, rails: &[Rail]) -> Option<Vec<bool>> {
+        if rails.is_empty() {
+            return None;
+        }
+        let mut mask = self.live.immovable.clone();
+        for rail in rails {
+            mask[rail.slot] = true;
+        }
+        Some(mask)
+    }
+
+    /// Move everything on rails along its orbit, after everything else has been integrated.
+    pub(crate) fn ride_rails(&mut self, rails: &[Rail], dt: f64) {
+        // Primaries come first, so they're already where they should be
+        for rail in rails {
+            let primary = self.live.kinemat(rail.primary_slot);
+            let Kinemat { pos, vel } = rail.elements.advanced(dt).to_kinemat(primary);
+            self.live.pos[rail.slot] = pos;
+            self.live.vel[rail.slot] = vel;
+        }
+    }
+}
@@ -0,0 +1,112 @@
+//! Keeps track of every Body a SolarSystem knows about, and hands out IDs for them.
+
+use crate::bodies::Body;
+use serde::{Deserialize, Serialize};
+use std::fmt;
+
+/// A handle to a body in a SolarSystem.
+/// Slots get reused once nothing needs the old body anymore, but the generation
+/// changes when that happens, so an old BodyId never points at the new body.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash, PartialOrd, Ord, Serialize, Deserialize)]
+pub struct BodyId {
+    index: u32,
+    generation: u32,
+}
+
+impl BodyId {
+    /// Which slot the body is in. Only unique among bodies that exist at the same time.
+    pub fn index(&self) -> usize {
+        self.index as usize
+    }
+
+    /// How many times the slot has been reused.
+    pub fn generation(&self) -> u32 {
+        self.generation
+    }
+}
+
+impl fmt::Display for BodyId {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "#{}.{}", self.index, self.generation)
+    }
+}
+
+/// A generational arena of bodies.
+#[derive(Clone, Default, Serialize, Deserialize)]
+pub(crate) struct Registry {
+    slots: Vec<Slot>,
+    /// Indices of slots that are empty and can be reused
+    free: Vec<u32>,
+}
+
+#[derive(Clone, Serialize, Deserialize)]
+struct Slot {
+    generation: u32,
+    body: Option<Body>,
+}
+
+impl Registry {
+    /// Store a body and get a fresh ID for it.
+    pub fn insert(&mut self, body: Body) -> BodyId {
+        match self.free.pop() {
+            Some(index) => {
+                let slot = &mut self.slots[index as usize];
+                slot.body = Some(body);
+                BodyId {
+                    index,
+                    generation: slot.generation,
+                }
+            }
+            None => {
+                self.slots.push(Slot {
+                    generation: 0,
+                    body: Some(body),
+                });
+                BodyId {
+                    index: self.slots.len() as u32 - 1,
+                    generation: 0,
+                }
+            }
+        }
+    }
+
+    /// Get the body an ID refers to, if it's still around.
+    pub fn get(&self, id: BodyId) -> Option<&Body> {
+        self.slots
+            .get(id.index())
+            .filter(|slot| slot.generation == id.generation)
+            .and_then(|slot| slot.body.as_ref())
+    }
+
+    /// Forget about a body. Its ID will never work again.
+    pub fn remove(&mut self, id: BodyId) {
+        if self.get(id).is_none() {
+            return;
+        }
+        let slot = &mut self.slots[id.index()];
+        slot.body = None;
+        slot.generation += 1;
+        self.free.push(id.index);
+    }
+
+    /// Every ID that currently refers to a body
+    pub fn ids(&self) -> impl Iterator<Item = BodyId> + '_ {
+        self.slots
+            .iter()
+            .enumerate()
+            .filter(|(_, slot)| slot.body.is_some())
+            .map(|(index, slot)| BodyId {
+                index: index as u32,
+                generation: slot.generation,
+            })
+    }
+}
+
+impl std::ops::Index<BodyId> for Registry {
+    type Output = Body;
+
+    fn index(&self, id: BodyId) -> &Body {
+        self.get(id)
+            .unwrap_or_else(|| panic!("Body {} doesn't exist anymore!", id))
+    }
+}
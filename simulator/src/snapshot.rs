@@ -0,0 +1,201 @@
+//! Handles writing a whole SolarSystem to a file and reading it back.
+//!
+//! A snapshot file is the magic bytes `ORBSNAP\0`, then the format version as a little-endian u32,
+//! then everything else encoded with bincode.
+
+use crate::bodies::Kinemat;
+use crate::branch::Branches;
+use crate::config::SimulationConfig;
+use crate::gravity::GravitySolver;
+use crate::history::History;
+use crate::integrator;
+use crate::lineage::Lineage;
+use crate::registry::Registry;
+use crate::storage::Storage;
+use crate::{BodyId, SolarSystem};
+use serde::{Deserialize, Serialize};
+use std::fmt;
+use std::fs::File;
+use std::io::{self, BufReader, BufWriter, Read, Write};
+use std::path::Path;
+
+const MAGIC: &[u8; 8] = b"ORBSNAP\0";
+/// The version of the snapshot format this writes.
+/// Bump this whenever anything that goes into a snapshot changes shape.
+pub const SNAPSHOT_VERSION: u32 = 9;
+
+/// Everything that goes into a snapshot, borrowed from a SolarSystem.
+/// The fields have to stay in the same order as `Snapshot`.
+#[derive(Serialize)]
+struct SnapshotRef<'a> {
+    bodies: &'a Registry,
+    live: Vec<(BodyId, Kinemat)>,
+    history: &'a History,
+    branches: &'a Branches,
+    config: &'a SimulationConfig,
+    frames_elapsed: usize,
+    time: f64,
+    lineage: &'a Lineage,
+    initial_energy: f64,
+    integrator: &'a str,
+    gravity_solver: GravitySolver,
+    rails: Vec<(BodyId, BodyId)>,
+}
+
+/// Everything that comes out of a snapshot.
+#[derive(Deserialize)]
+struct Snapshot {
+    bodies: Registry,
+    live: Vec<(BodyId, Kinemat)>,
+    history: History,
+    branches: Branches,
+    config: SimulationConfig,
+    frames_elapsed: usize,
+    time: f64,
+    lineage: Lineage,
+    initial_energy: f64,
+    integrator: String,
+    gravity_solver: GravitySolver,
+    rails: Vec<(BodyId, BodyId)>,
+}
+
+/// Something that went wrong reading or writing a snapshot.
+#[derive(Debug)]
+pub enum SnapshotError {
+    /// Couldn't read or write the file
+    Io(io::Error),
+    /// The file doesn't start with the magic bytes, so it's not a snapshot at all
+    NotASnapshot,
+    /// The snapshot was written by a different version of the format
+    UnsupportedVersion(u32),
+    /// The snapshot is a snapshot, but the inside is garbled
+    Corrupt(bincode::Error),
+    /// The snapshot was running with an integrator this doesn't know about
+    UnknownIntegrator(String),
+}
+
+impl fmt::Display for SnapshotError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            SnapshotError::Io(err) => write!(f, "couldn't access snapshot: {}", err),
+            SnapshotError::NotASnapshot => write!(f, "that's not a snapshot file"),
+            SnapshotError::UnsupportedVersion(version) => write!(
+                f,
+                "snapshot is version {}, but only version {} is supported",
+                version, SNAPSHOT_VERSION
+            ),
+            SnapshotError::Corrupt(err) => write!(f, "snapshot is corrupt: {}", err),
+            SnapshotError::UnknownIntegrator(name) => {
+                write!(f, "snapshot uses an unknown integrator `{}`", name)
+            }
+        }
+    }
+}
+
+impl std::error::Error for SnapshotError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            SnapshotError::Io(err) => Some(err),
+            SnapshotError::Corrupt(err) => Some(err),
+            _ => None,
+        }
+    }
+}
+
+impl From<io::Error> for SnapshotError {
+    fn from(err: io::Error) -> Self {
+        SnapshotError::Io(err)
+    }
+}
+
+impl From<bincode::Error> for SnapshotError {
+    fn from(err: bincode::Error) -> Self {
+        SnapshotError::Corrupt(err)
+    }
+}
+
+impl SolarSystem {
+    /// Write everything about the simulation, including all its saves, so it can be picked back up later.
+    /// The only thing left out is collision events that haven't been drained yet.
+    /// The integrator is written by name, so it has to be one `integrator::from_name` knows.
+    pub fn write_snapshot<W: Write>(&self, mut writer: W) -> Result<(), SnapshotError> {
+        writer.write_all(MAGIC)?;
+        writer.write_all(&SNAPSHOT_VERSION.to_le_bytes())?;
+        let snapshot = SnapshotRef {
+            bodies: &self.bodies,
+            live: self.live.kinemats().collect(),
+            history: &self.history,
+            branches: &self.branches,
+            config: &self.config,
+            frames_elapsed: self.frames_elapsed,
+            time: self.time,
+            lineage: &self.lineage,
+            initial_energy: self.initial_energy,
+            integrator: self.integrator.name(),
+            gravity_solver: self.gravity_solver,
+            rails: self
+                .rails
+                .iter()
+                .map(|(&id, &primary)| (id, primary))
+                .collect(),
+        };
+        bincode::serialize_into(&mut writer, &snapshot)?;
+        writer.flush()?;
+        Ok(())
+    }
+
+    /// Read a simulation back from a snapshot made with `write_snapshot`.
+    pub fn read_snapshot<R: Read>(mut reader: R) -> Result<SolarSystem, SnapshotError> {
+        let mut magic = [0; 8];
+        reader.read_exact(&mut magic)?;
+        if &magic != MAGIC {
+            return Err(SnapshotError::NotASnapshot);
+        }
+        let mut version = [0; 4];
+        reader.read_exact(&mut version)?;
+        let version = u32::from_le_bytes(version);
+        if version != SNAPSHOT_VERSION {
+            return Err(SnapshotError::UnsupportedVersion(version));
+        }
+
+        let snapshot: Snapshot = bincode::deserialize_from(reader)?;
+        let integrator = integrator::from_name(&snapshot.integrator)
+            .ok_or(SnapshotError::UnknownIntegrator(snapshot.integrator))?;
+        let mut live = Storage::default();
+        for (id, kmat) in snapshot.live {
+            let body = snapshot.bodies.get(id).ok_or_else(|| {
+                SnapshotError::Corrupt(Box::new(bincode::ErrorKind::Custom(format!(
+                    "body {} is alive but doesn't exist",
+                    id
+                ))))
+            })?;
+            live.push(id, body, kmat);
+        }
+
+        Ok(SolarSystem {
+            bodies: snapshot.bodies,
+            live,
+            history: snapshot.history,
+            branches: snapshot.branches,
+            config: snapshot.config,
+            frames_elapsed: snapshot.frames_elapsed,
+            time: snapshot.time,
+            collision_events: Vec::new(),
+            lineage: snapshot.lineage,
+            initial_energy: snapshot.initial_energy,
+            integrator: integrator.into(),
+            gravity_solver: snapshot.gravity_solver,
+            rails: snapshot.rails.into_iter().collect(),
+        })
+    }
+
+    /// Write a snapshot to a file, replacing it if it's already there.
+    pub fn save_snapshot<P: AsRef<Path>>(&self, path: P) -> Result<(), SnapshotError> {
+        self.write_snapshot(BufWriter::new(File::create(path)?))
+    }
+
+    /// Read a simulation back from a snapshot file.
+    pub fn load_snapshot<P: AsRef<Path>>(path: P) -> Result<SolarSystem, SnapshotError> {
+        SolarSystem::read_snapshot(BufReader::new(File::open(path)?))
+    }
+}
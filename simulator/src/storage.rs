@@ -0,0 +1,96 @@
+//! Holds the state of every live orbiter as a bunch of parallel arrays.
+//!
+//! The physics loops only ever look at a couple of these at a time, so keeping
+//! them in separate contiguous arrays is much kinder to the cache than a map of structs.
+
+use crate::bodies::{Body, Kinemat};
+use crate::registry::BodyId;
+use euclid::default::{Point2D, Vector2D};
+use std::collections::HashMap;
+
+#[derive(Clone, Default)]
+pub(crate) struct Storage {
+    /// The ID of the orbiter in each slot
+    pub ids: Vec<BodyId>,
+    pub pos: Vec<Point2D<f64>>,
+    pub vel: Vec<Vector2D<f64>>,
+    pub mass: Vec<f64>,
+    pub radius: Vec<f64>,
+    pub immovable: Vec<bool>,
+    pub test_particle: Vec<bool>,
+    /// Maps each ID to the slot it's in.
+    /// This is only ever looked up in and never iterated over, so its random order can't leak into the simulation.
+    slots: HashMap<BodyId, usize>,
+}
+
+impl Storage {
+    pub fn len(&self) -> usize {
+        self.ids.len()
+    }
+
+    /// Which slot an ID is in, if it's alive.
+    pub fn slot(&self, id: BodyId) -> Option<usize> {
+        self.slots.get(&id).copied()
+    }
+
+    /// The kinemat in a slot
+    pub fn kinemat(&self, slot: usize) -> Kinemat {
+        Kinemat::new(self.pos[slot], self.vel[slot])
+    }
+
+    /// How much kinetic energy the orbiter in a slot has
+    pub fn kinetic_energy(&self, slot: usize) -> f64 {
+        0.5 * self.mass[slot] * self.vel[slot].square_length()
+    }
+
+    /// Every ID and its kinemat, in slot order
+    pub fn kinemats(&self) -> impl Iterator<Item = (BodyId, Kinemat)> + '_ {
+        (0..self.len()).map(move |slot| (self.ids[slot], self.kinemat(slot)))
+    }
+
+    /// Add an orbiter to the end.
+    /// Things that are immovable never get any velocity, and test particles don't weigh anything.
+    pub fn push(&mut self, id: BodyId, body: &Body, kmat: Kinemat) {
+        self.slots.insert(id, self.ids.len());
+        self.ids.push(id);
+        self.pos.push(kmat.pos);
+        self.vel.push(if body.immovable {
+            Vector2D::zero()
+        } else {
+            kmat.vel
+        });
+        self.mass.push(body.gravitating_mass());
+        self.radius.push(body.radius);
+        self.immovable.push(body.immovable);
+        self.test_particle.push(body.test_particle);
+    }
+
+    /// Stop storing all these IDs.
+    /// Everything else keeps its order, so this is done all at once.
+    pub fn remove(&mut self, ids: &[BodyId]) {
+        let doomed: Vec<bool> = self.ids.iter().map(|id| ids.contains(id)).collect();
+        retain_slots(&mut self.ids, &doomed);
+        retain_slots(&mut self.pos, &doomed);
+        retain_slots(&mut self.vel, &doomed);
+        retain_slots(&mut self.mass, &doomed);
+        retain_slots(&mut self.radius, &doomed);
+        retain_slots(&mut self.immovable, &doomed);
+        retain_slots(&mut self.test_particle, &doomed);
+
+        self.slots = self
+            .ids
+            .iter()
+            .enumerate()
+            .map(|(slot, &id)| (id, slot))
+            .collect();
+    }
+}
+
+/// Remove every slot marked as doomed from one of the arrays.
+fn retain_slots<T>(array: &mut Vec<T>, doomed: &[bool]) {
+    let mut slot = 0;
+    array.retain(|_| {
+        slot += 1;
+        !doomed[slot - 1]
+    });
+}
@@ -0,0 +1,92 @@
+mod common;
+
+use common::lcg;
+use euclid::default::{Point2D, Vector2D};
+use simulator::config::SimulationConfig;
+use simulator::gravity::{accelerations, GravitySolver};
+
+#[test]
+fn barnes_hut_matches_direct_sum() {
+    // A star with a lumpy asteroid belt and a few planets, like AsteroidsBuilder makes
+    let mut seed = 0xdead_beef;
+    let mut pos = vec![Point2D::zero()];
+    let mut masses = vec![1.9884e30];
+    for _ in 0..1000 {
+        let theta = lcg(&mut seed) * std::f64::consts::PI * 2.0;
+        let orbit = 3.14e11 + lcg(&mut seed) * 1.95e11;
+        pos.push(Point2D::new(theta.cos() * orbit, theta.sin() * orbit));
+        masses.push(lcg(&mut seed) * 1e21);
+    }
+    for &(x, y, mass) in &[
+        (1.5e11, 0.0, 6e24),
+        (0.0, 7.8e11, 1.9e27),
+        (-2.3e11, 1e9, 6.4e23),
+    ] {
+        pos.push(Point2D::new(x, y));
+        masses.push(mass);
+    }
+    let count = pos.len();
+    let config = SimulationConfig::default();
+    let immovable = vec![false; count];
+
+    let mut exact = vec![Vector2D::zero(); count];
+    accelerations(
+        GravitySolver::Direct,
+        &config,
+        &pos,
+        &masses,
+        &immovable,
+        &mut exact,
+    );
+    let mut approx = vec![Vector2D::zero(); count];
+    accelerations(
+        GravitySolver::BarnesHut { theta: 0.5 },
+        &config,
+        &pos,
+        &masses,
+        &immovable,
+        &mut approx,
+    );
+
+    for (i, (e, a)) in exact.iter().zip(approx.iter()).enumerate() {
+        let error = (*e - *a).length() / e.length();
+        assert!(error < 0.001, "orbiter {} is off by {}", i, error);
+    }
+}
+
+#[test]
+fn barnes_hut_with_zero_theta_is_exact() {
+    let pos = vec![
+        Point2D::new(0.0, 0.0),
+        Point2D::new(1e9, 0.0),
+        Point2D::new(0.0, -3e9),
+        Point2D::new(-2e9, 5e8),
+    ];
+    let masses = vec![2e30, 6e24, 7e22, 1e26];
+    let config = SimulationConfig::default();
+    let immovable = vec![true, false, false, false];
+
+    let mut exact = vec![Vector2D::zero(); 4];
+    accelerations(
+        GravitySolver::Direct,
+        &config,
+        &pos,
+        &masses,
+        &immovable,
+        &mut exact,
+    );
+    let mut approx = vec![Vector2D::zero(); 4];
+    accelerations(
+        GravitySolver::BarnesHut { theta: 0.0 },
+        &config,
+        &pos,
+        &masses,
+        &immovable,
+        &mut approx,
+    );
+
+    assert_eq!(approx[0], Vector2D::zero());
+    for (e, a) in exact.iter().zip(approx.iter()).skip(1) {
+        assert!((*e - *a).length() <= e.length() * 1e-12);
+    }
+}
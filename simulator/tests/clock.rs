@@ -0,0 +1,50 @@
+use simulator::clock::Epoch;
+
+#[test]
+fn known_dates() {
+    assert_eq!(Epoch::J2000.to_string(), "2000-01-01 12:00:00 TDB");
+    let parse = |s: &str| s.parse::<Epoch>().unwrap().julian_date;
+    assert_eq!(parse("J2000"), 2_451_545.0);
+    assert_eq!(parse("2000-01-01T12:00:00"), 2_451_545.0);
+    assert_eq!(parse("1999-12-31 12:00"), 2_451_544.0);
+    // Sputnik 1 launched on 1957 October 4.81, which Meeus gives as JD 2436116.31
+    assert!((parse("1957-10-04 19:26:24") - 2_436_116.31).abs() < 1e-6);
+    // Before the Gregorian calendar existed, it's just extended backwards
+    assert_eq!(parse("-4713-11-24 12:00:00"), 0.0);
+}
+
+#[test]
+fn dates_round_trip() {
+    for date in &[
+        "2024-02-29 23:59:59",
+        "1582-10-15 00:00:00",
+        "2100-03-01 06:30:15",
+        "0001-01-01 00:00:00",
+    ] {
+        let epoch: Epoch = date.parse().unwrap();
+        assert_eq!(epoch.calendar().to_string(), *date);
+    }
+}
+
+#[test]
+fn counting_seconds() {
+    let later = Epoch::J2000.after(1.5 * 86_400.0);
+    assert_eq!(later.to_string(), "2000-01-03 00:00:00 TDB");
+    assert_eq!(later.seconds_since(Epoch::J2000), 1.5 * 86_400.0);
+}
+
+#[test]
+fn bad_dates_are_errors() {
+    for date in &[
+        "yesterday",
+        "2000-13-01",
+        "2000-01-01 25:00:00",
+        "2000-01",
+        "2024-02-31",
+        "2023-04-31",
+        "2023-02-29",
+        "1900-02-29",
+    ] {
+        assert!(date.parse::<Epoch>().is_err(), "{} parsed", date);
+    }
+}
@@ -0,0 +1,27 @@
+//! Things the tests share.
+// Every test file only uses some of these
+#![allow(dead_code)]
+
+use simulator::bodies::Body;
+
+/// A plain white body that collides however the config says
+pub fn body(name: &str, mass: f64, radius: f64) -> Body {
+    Body {
+        mass,
+        radius,
+        color: 0xffffff,
+        outline: 0xffffff,
+        name: name.to_string(),
+        immovable: false,
+        test_particle: false,
+        collision: None,
+    }
+}
+
+/// A tiny deterministic random number generator, so the tests don't need `rand`.
+pub fn lcg(state: &mut u64) -> f64 {
+    *state = state
+        .wrapping_mul(6364136223846793005)
+        .wrapping_add(1442695040888963407);
+    (*state >> 11) as f64 / (1u64 << 53) as f64
+}
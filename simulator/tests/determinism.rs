@@ -0,0 +1,161 @@
+mod common;
+
+use common::{body, lcg};
+use euclid::default::{Point2D, Vector2D};
+use simulator::bodies::{Body, Kinemat, Orbiter};
+use simulator::config::{CollisionModel, Fragmentation, SimulationConfig};
+use simulator::gravity::GravitySolver;
+use simulator::integrator::Leapfrog;
+use simulator::SolarSystem;
+
+/// A crowded swarm of fat planetesimals around a star, so lots of things hit each other at once.
+fn crowded_system(seed: u64) -> SolarSystem {
+    let mut seed = seed;
+    let mut orbiters = vec![Orbiter(
+        Body {
+            mass: 1.9884e30,
+            radius: 6.957e8,
+            color: 0xffff00,
+            outline: 0xff8000,
+            name: "Star".to_string(),
+            immovable: false,
+            test_particle: false,
+            collision: None,
+        },
+        Kinemat::zero(),
+    )];
+    for n in 0..100 {
+        let theta = lcg(&mut seed) * std::f64::consts::PI * 2.0;
+        let orbit = 1e10 + lcg(&mut seed) * 2e9;
+        let speed = (6.674e-11 * 1.9884e30 / orbit).sqrt() * (0.8 + lcg(&mut seed) * 0.4);
+        orbiters.push(Orbiter(
+            Body {
+                mass: 1e20 + lcg(&mut seed) * 1e22,
+                radius: 2e7 + lcg(&mut seed) * 2e7,
+                color: 0x808080,
+                outline: 0x404040,
+                name: format!("Rock {}", n),
+                immovable: false,
+                test_particle: false,
+                // Mix in some bouncy ones so every kind of collision happens
+                collision: if n % 5 == 0 {
+                    Some(CollisionModel::Elastic { restitution: 0.5 })
+                } else {
+                    None
+                },
+            },
+            Kinemat::new(
+                Point2D::new(theta.cos() * orbit, theta.sin() * orbit),
+                Vector2D::new(-theta.sin() * speed, theta.cos() * speed),
+            ),
+        ));
+    }
+
+    let config = SimulationConfig::builder()
+        .fragmentation(Some(Fragmentation {
+            threshold: 1e7,
+            min_fragment_mass: 1e20,
+            seed: 7,
+            ..Fragmentation::default()
+        }))
+        .build();
+    let mut solar_system = SolarSystem::new(orbiters, config, Box::new(Leapfrog));
+    solar_system.set_gravity_solver(GravitySolver::BarnesHut { theta: 0.5 });
+    solar_system
+}
+
+#[test]
+fn same_scenario_gives_same_state() {
+    let mut first = crowded_system(0x5eed);
+    let mut second = crowded_system(0x5eed);
+    for _ in 0..500 {
+        first.update(600.0);
+        second.update(600.0);
+    }
+
+    let first_events: Vec<_> = first.drain_collision_events().collect();
+    let second_events: Vec<_> = second.drain_collision_events().collect();
+    assert!(
+        !first_events.is_empty(),
+        "nothing collided, so this didn't test much"
+    );
+    assert_eq!(first_events.len(), second_events.len());
+    for (a, b) in first_events.iter().zip(second_events.iter()) {
+        assert_eq!(a.step, b.step);
+        assert_eq!(a.bodies, b.bodies);
+        assert_eq!(a.kind, b.kind);
+        assert_eq!(a.results, b.results);
+        assert_eq!(a.impact_speed.to_bits(), b.impact_speed.to_bits());
+    }
+
+    let (first, second) = (first.get_orbiters(), second.get_orbiters());
+    assert_eq!(
+        first.keys().collect::<Vec<_>>(),
+        second.keys().collect::<Vec<_>>()
+    );
+    for (Orbiter(a_body, a_kmat), Orbiter(b_body, b_kmat)) in first.values().zip(second.values()) {
+        assert_eq!(a_body.name, b_body.name);
+        assert_eq!(a_body.mass.to_bits(), b_body.mass.to_bits());
+        // Not just close, exactly the same
+        assert_eq!(a_kmat.pos.x.to_bits(), b_kmat.pos.x.to_bits());
+        assert_eq!(a_kmat.pos.y.to_bits(), b_kmat.pos.y.to_bits());
+        assert_eq!(a_kmat.vel.x.to_bits(), b_kmat.vel.x.to_bits());
+        assert_eq!(a_kmat.vel.y.to_bits(), b_kmat.vel.y.to_bits());
+    }
+}
+
+/// Mix the bits of `value` into an FNV-1a hash, which stays the same between Rust versions unlike `DefaultHasher`.
+fn fnv(hash: u64, value: f64) -> u64 {
+    value
+        .to_bits()
+        .to_le_bytes()
+        .iter()
+        .fold(hash, |hash, &byte| {
+            (hash ^ byte as u64).wrapping_mul(0x100000001b3)
+        })
+}
+
+/// `cargo test` and `cargo test --features parallel` both have to come out to exactly these hashes,
+/// so the parallel feature can't change the results.
+/// Nothing here needs anything but arithmetic and square roots, which are the same everywhere.
+#[test]
+fn parallel_gives_the_same_results_as_serial() {
+    for &(solver, expected) in &[
+        (GravitySolver::Direct, 3176334639220960736),
+        (
+            GravitySolver::BarnesHut { theta: 0.5 },
+            12949183553727474943,
+        ),
+    ] {
+        let mut seed = 0xfeed;
+        let orbiters = (0..300)
+            .map(|n| {
+                let mut coord = || (lcg(&mut seed) - 0.5) * 2e11;
+                let pos = Point2D::new(coord(), coord());
+                let vel = Vector2D::new(coord(), coord()) * 1e-7;
+                Orbiter(
+                    body(&n.to_string(), 1e24 + lcg(&mut seed) * 1e26, 1.0),
+                    Kinemat::new(pos, vel),
+                )
+            })
+            .collect();
+        let mut solar_system =
+            SolarSystem::new(orbiters, SimulationConfig::default(), Box::new(Leapfrog));
+        solar_system.set_gravity_solver(solver);
+        for _ in 0..100 {
+            solar_system.update(3600.0);
+        }
+
+        let hash =
+            solar_system
+                .get_orbiters()
+                .values()
+                .fold(0xcbf29ce484222325, |hash, orbiter| {
+                    let Kinemat { pos, vel } = orbiter.1;
+                    [pos.x, pos.y, vel.x, vel.y]
+                        .iter()
+                        .fold(hash, |hash, &value| fnv(hash, value))
+                });
+        assert_eq!(hash, expected, "with {:?}", solver);
+    }
+}
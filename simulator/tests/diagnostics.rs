@@ -0,0 +1,118 @@
+mod common;
+
+use common::body;
+use euclid::default::{Point2D, Vector2D};
+use simulator::bodies::{Body, Kinemat, Orbiter};
+use simulator::config::SimulationConfig;
+use simulator::integrator::Leapfrog;
+use simulator::{CollisionKind, SolarSystem, GRAV_CONSTANT};
+
+/// A star and a heavy planet going around each other on an eccentric orbit, the whole thing drifting sideways
+fn binary() -> SolarSystem {
+    let orbiters = vec![
+        Orbiter(
+            body("Star", 2e30, 7e8),
+            Kinemat::new(Point2D::new(0.0, 0.0), Vector2D::new(3000.0, -1000.0)),
+        ),
+        Orbiter(
+            body("Planet", 2e29, 7e7),
+            Kinemat::new(Point2D::new(1.5e11, 0.0), Vector2D::new(3000.0, 35000.0)),
+        ),
+    ];
+    SolarSystem::new(orbiters, SimulationConfig::default(), Box::new(Leapfrog))
+}
+
+#[test]
+fn two_bodies_conserve_momentum_and_angular_momentum() {
+    let mut solar_system = binary();
+    let momentum = solar_system.momentum();
+    let angular_momentum = solar_system.angular_momentum();
+    // A few orbits
+    for _ in 0..20_000 {
+        solar_system.update(3600.0);
+    }
+    let momentum_error = (solar_system.momentum() - momentum).length() / momentum.length();
+    assert!(
+        momentum_error < 1e-10,
+        "momentum is off by {}",
+        momentum_error
+    );
+    let angular_momentum_error =
+        ((solar_system.angular_momentum() - angular_momentum) / angular_momentum).abs();
+    assert!(
+        angular_momentum_error < 1e-10,
+        "angular momentum is off by {}",
+        angular_momentum_error
+    );
+    // It actually went somewhere
+    let star = solar_system.get_orbiters().values().next().unwrap().1;
+    assert!(star.pos.to_vector().length() > 1e11);
+}
+
+#[test]
+fn merging_conserves_momentum() {
+    let orbiters = vec![
+        Orbiter(
+            body("Big", 6e24, 6.4e6),
+            Kinemat::new(Point2D::new(0.0, 0.0), Vector2D::new(1000.0, 0.0)),
+        ),
+        Orbiter(
+            body("Small", 7e22, 1.7e6),
+            Kinemat::new(Point2D::new(7e6, 1e6), Vector2D::new(-20000.0, 5000.0)),
+        ),
+    ];
+    let mut solar_system =
+        SolarSystem::new(orbiters, SimulationConfig::default(), Box::new(Leapfrog));
+    let momentum = solar_system.momentum();
+    solar_system.update(1e-3);
+
+    let events: Vec<_> = solar_system.drain_collision_events().collect();
+    assert_eq!(events.len(), 1);
+    assert_eq!(events[0].kind, CollisionKind::Merged);
+    assert_eq!(solar_system.get_orbiters().len(), 1);
+    let momentum_error = (solar_system.momentum() - momentum).length() / momentum.length();
+    assert!(
+        momentum_error < 1e-12,
+        "momentum is off by {}",
+        momentum_error
+    );
+}
+
+#[test]
+fn potential_energy_counts_every_pair_with_a_puller_once() {
+    let at = |x: f64, y: f64| Kinemat::new(Point2D::new(x, y), Vector2D::zero());
+    let orbiters = vec![
+        Orbiter(body("Sun", 2e30, 7e8), at(0.0, 0.0)),
+        Orbiter(body("Planet", 6e24, 6.4e6), at(1.5e11, 0.0)),
+        // Too light to pull, but still gets pulled
+        Orbiter(body("Rock", 1e10, 1.0), at(0.0, 4e11)),
+        Orbiter(body("Pebble", 1e10, 1.0), at(0.0, -4e11)),
+        Orbiter(
+            Body {
+                test_particle: true,
+                ..body("Particle", 1e28, 1.0)
+            },
+            at(2e11, 2e11),
+        ),
+    ];
+    let config = SimulationConfig::builder().min_pull_mass(1e12).build();
+    let solar_system = SolarSystem::new(orbiters, config, Box::new(Leapfrog));
+
+    let pair =
+        |m1: f64, m2: f64, dx: f64, dy: f64| -GRAV_CONSTANT * m1 * m2 / (dx * dx + dy * dy).sqrt();
+    let expected = pair(2e30, 6e24, 1.5e11, 0.0)
+        + 2.0 * pair(2e30, 1e10, 0.0, 4e11)
+        + 2.0 * pair(6e24, 1e10, 1.5e11, 4e11);
+    let error = ((solar_system.potential_energy() - expected) / expected).abs();
+    assert!(error < 1e-12, "potential energy is off by {}", error);
+}
+
+#[test]
+fn energy_drift_is_in_joules_when_there_was_no_energy() {
+    let orbiters = vec![Orbiter(body("Rock", 1e10, 1.0), Kinemat::zero())];
+    let mut solar_system =
+        SolarSystem::new(orbiters, SimulationConfig::default(), Box::new(Leapfrog));
+    assert_eq!(solar_system.energy_drift(), 0.0);
+    solar_system.update(1.0);
+    assert_eq!(solar_system.energy_drift(), 0.0);
+}
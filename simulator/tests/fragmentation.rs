@@ -0,0 +1,116 @@
+mod common;
+
+use common::body;
+use euclid::default::{Point2D, Vector2D};
+use simulator::bodies::{Kinemat, Orbiter};
+use simulator::config::{Fragmentation, SimulationConfig};
+use simulator::integrator::Leapfrog;
+use simulator::{CollisionKind, SolarSystem};
+
+const MARS_MASS: f64 = 6.4e23;
+const MARS_RADIUS: f64 = 3.39e6;
+
+/// Two Mars-sized planets already touching, hitting each other off center at 20 km/s
+fn smash(seed: u64) -> SolarSystem {
+    let orbiters = vec![
+        Orbiter(
+            body("Ares", MARS_MASS, MARS_RADIUS),
+            Kinemat::new(Point2D::new(-3e6, 0.0), Vector2D::new(15000.0, 2000.0)),
+        ),
+        Orbiter(
+            body("Eris", MARS_MASS / 3.0, MARS_RADIUS * 0.7),
+            Kinemat::new(Point2D::new(2e6, 1e6), Vector2D::new(-5000.0, 0.0)),
+        ),
+    ];
+    let config = SimulationConfig::builder()
+        .fragmentation(Some(Fragmentation {
+            threshold: 1e7,
+            max_fragments: 40,
+            min_fragment_mass: 1e21,
+            seed,
+            ..Fragmentation::default()
+        }))
+        .build();
+    SolarSystem::new(orbiters, config, Box::new(Leapfrog))
+}
+
+#[test]
+fn fragmenting_conserves_everything_and_nothing_overlaps() {
+    let dt = 1e-3;
+    for seed in 0..20 {
+        let mut solar_system = smash(seed);
+        let mass_before: f64 = solar_system
+            .get_orbiters()
+            .values()
+            .map(|orbiter| orbiter.0.mass)
+            .sum();
+        let momentum_before = solar_system.momentum();
+        let center_before = solar_system.center_of_mass();
+        solar_system.update(dt);
+
+        let events: Vec<_> = solar_system.drain_collision_events().collect();
+        assert_eq!(events.len(), 1);
+        assert_eq!(events[0].kind, CollisionKind::Fragmented);
+        let orbiters = solar_system.get_orbiters();
+        assert_eq!(orbiters.len(), events[0].results.len());
+        assert!(orbiters.len() > 2, "only {} pieces", orbiters.len());
+
+        let mass_after: f64 = orbiters.values().map(|orbiter| orbiter.0.mass).sum();
+        assert!((mass_after - mass_before).abs() < 1e-12 * mass_before);
+        let momentum_error = (solar_system.momentum() - momentum_before).length();
+        assert!(
+            momentum_error < 1e-9 * momentum_before.length(),
+            "momentum is off by {}",
+            momentum_error
+        );
+        // The center of mass just keeps drifting along like nothing happened
+        let center_expected = center_before + momentum_before / mass_before * dt;
+        let center_error = (solar_system.center_of_mass() - center_expected).length();
+        assert!(
+            center_error < 1e-3,
+            "center of mass is off by {} m",
+            center_error
+        );
+
+        let pieces: Vec<_> = orbiters.values().collect();
+        for (n, a) in pieces.iter().enumerate() {
+            for b in pieces[n + 1..].iter() {
+                let gap = (b.1.pos - a.1.pos).length() - a.0.radius - b.0.radius;
+                assert!(
+                    gap > 0.0,
+                    "{} and {} overlap by {} m",
+                    a.0.name,
+                    b.0.name,
+                    -gap
+                );
+            }
+        }
+    }
+}
+
+#[test]
+fn debris_is_never_too_small_and_energy_never_goes_up() {
+    for seed in 0..20 {
+        let mut solar_system = smash(seed);
+        let energy_before = solar_system.total_energy();
+        solar_system.update(1e-3);
+
+        let events: Vec<_> = solar_system.drain_collision_events().collect();
+        assert_eq!(events[0].kind, CollisionKind::Fragmented);
+        for orbiter in solar_system.get_orbiters().values() {
+            assert!(
+                orbiter.0.mass >= 1e21,
+                "{} is only {} kg",
+                orbiter.0.name,
+                orbiter.0.mass
+            );
+        }
+        let energy_after = solar_system.total_energy();
+        assert!(
+            energy_after <= energy_before,
+            "energy went from {} J to {} J",
+            energy_before,
+            energy_after
+        );
+    }
+}
@@ -0,0 +1,120 @@
+mod common;
+
+use common::body;
+use euclid::default::{Point2D, Vector2D};
+use simulator::bodies::{Kinemat, Orbiter};
+use simulator::config::SimulationConfig;
+use simulator::hierarchy::PrimaryChange;
+use simulator::integrator::Leapfrog;
+use simulator::{SolarSystem, GRAV_CONSTANT};
+
+const AU: f64 = 1.496e11;
+const SUN_MASS: f64 = 1.989e30;
+const EARTH_MASS: f64 = 5.972e24;
+
+/// Going counterclockwise around something of `mass` at `kmat`, `dist` meters away along x, in a circle
+fn circling(kmat: Kinemat, mass: f64, dist: f64) -> Kinemat {
+    let speed = (GRAV_CONSTANT * mass / dist.abs()).sqrt().copysign(dist);
+    Kinemat::new(
+        kmat.pos + Vector2D::new(dist, 0.0),
+        kmat.vel + Vector2D::new(0.0, speed),
+    )
+}
+
+/// The Sun, the Earth, Jupiter, a rock, and a comet, with the rock `rock_dist` meters past the Earth,
+/// and the comet going `comet_speed` times escape speed
+fn system(rock_dist: f64, comet_speed: f64) -> SolarSystem {
+    let sun = Kinemat::zero();
+    let earth = circling(sun, SUN_MASS, AU);
+    let escape = (2.0 * GRAV_CONSTANT * SUN_MASS / (3.0 * AU)).sqrt();
+    let orbiters = vec![
+        Orbiter(body("Sun", SUN_MASS, 1.0), sun),
+        Orbiter(body("Earth", EARTH_MASS, 1.0), earth),
+        Orbiter(
+            body("Moon", 7.3e22, 1.0),
+            circling(earth, EARTH_MASS, 3.84e8),
+        ),
+        Orbiter(
+            body("Jupiter", 1.9e27, 1.0),
+            circling(sun, SUN_MASS, -5.2 * AU),
+        ),
+        Orbiter(
+            body("Rock", 1e15, 1.0),
+            circling(earth, EARTH_MASS, rock_dist),
+        ),
+        Orbiter(
+            body("Comet", 1e13, 1.0),
+            Kinemat::new(
+                Point2D::new(0.0, 3.0 * AU),
+                Vector2D::new(comet_speed * escape, 0.0),
+            ),
+        ),
+    ];
+    SolarSystem::new(orbiters, SimulationConfig::default(), Box::new(Leapfrog))
+}
+
+#[test]
+fn finds_what_orbits_what() {
+    let solar_system = system(1e9, 0.9);
+    let ids: Vec<_> = solar_system.get_orbiters().keys().copied().collect();
+    let (sun, earth, moon, jupiter, rock, comet) = (ids[0], ids[1], ids[2], ids[3], ids[4], ids[5]);
+    let hierarchy = solar_system.get_hierarchy();
+
+    assert_eq!(hierarchy.roots(), &[sun]);
+    assert_eq!(hierarchy.primary(sun), None);
+    // Heaviest first
+    assert_eq!(hierarchy.satellites(sun), &[jupiter, earth, comet]);
+    assert_eq!(hierarchy.satellites(earth), &[moon, rock]);
+    assert_eq!(hierarchy.primary(moon), Some(earth));
+
+    // The Earth's Hill sphere is about 1.5 million km
+    let hill = hierarchy.sphere_of_influence(earth).unwrap();
+    assert!((hill - 1.5e9).abs() < 0.05e9, "Hill sphere is {} m", hill);
+    assert_eq!(hierarchy.sphere_of_influence(sun), Some(f64::INFINITY));
+
+    let walked: Vec<_> = hierarchy
+        .walk()
+        .into_iter()
+        .map(|(id, depth)| (solar_system.get_orbiter(id).unwrap().0.name, depth))
+        .collect();
+    let expected = [
+        ("Sun", 0),
+        ("Jupiter", 1),
+        ("Earth", 1),
+        ("Moon", 2),
+        ("Rock", 2),
+        ("Comet", 1),
+    ];
+    let expected: Vec<_> = expected
+        .iter()
+        .map(|&(name, depth)| (name.to_string(), depth))
+        .collect();
+    assert_eq!(walked, expected);
+}
+
+#[test]
+fn notices_captures_and_escapes() {
+    let before = system(3e9, 0.9);
+    let after = system(1e9, 1.1);
+    let ids: Vec<_> = before.get_orbiters().keys().copied().collect();
+    let (sun, earth, rock, comet) = (ids[0], ids[1], ids[4], ids[5]);
+
+    let changes = after.get_hierarchy().changes_since(&before.get_hierarchy());
+    assert_eq!(
+        changes,
+        vec![
+            PrimaryChange {
+                id: rock,
+                old: Some(sun),
+                new: Some(earth)
+            },
+            PrimaryChange {
+                id: comet,
+                old: Some(sun),
+                new: None
+            },
+        ]
+    );
+    // A comet that's escaping everything doesn't orbit anything
+    assert_eq!(after.get_hierarchy().roots(), &[sun, comet]);
+}
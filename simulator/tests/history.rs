@@ -0,0 +1,309 @@
+mod common;
+
+use common::body;
+use euclid::default::{Point2D, Vector2D};
+use simulator::bodies::{Body, Kinemat, Orbiter};
+use simulator::config::{HistoryPolicy, SimulationConfig};
+use simulator::integrator::Leapfrog;
+use simulator::{BodyId, HistoryError, SolarSystem};
+use std::collections::BTreeMap;
+
+/// The Sun and the Earth, saving every 10 steps
+fn sun_and_earth(history: HistoryPolicy) -> SolarSystem {
+    let orbiters = vec![
+        Orbiter(body("Sun", 2e30, 1e6), Kinemat::zero()),
+        Orbiter(
+            body("Earth", 6e24, 1e6),
+            Kinemat::new(Point2D::new(1.5e11, 0.0), Vector2D::new(0.0, 29780.0)),
+        ),
+    ];
+    let config = SimulationConfig::builder().history(history).build();
+    SolarSystem::new(orbiters, config, Box::new(Leapfrog))
+}
+
+#[test]
+fn restoring_goes_back_in_time() {
+    let mut solar_system = sun_and_earth(HistoryPolicy {
+        save_every: 10,
+        keyframe_every: 4,
+        ..HistoryPolicy::default()
+    });
+    for _ in 0..100 {
+        solar_system.update(3600.0);
+    }
+    let saves = solar_system.list_saves();
+    assert_eq!(saves.len(), 10);
+    assert_eq!(saves[3].step, 30);
+    assert_eq!(saves[3].time, 30.0 * 3600.0);
+
+    // Looking doesn't touch anything
+    let earth = |orbiters: BTreeMap<BodyId, Orbiter>| orbiters.values().nth(1).unwrap().1;
+    let now = earth(solar_system.get_orbiters());
+    let then = earth(solar_system.preview_save(3).unwrap());
+    assert_eq!(earth(solar_system.get_orbiters()).pos, now.pos);
+    assert_eq!(solar_system.get_time(), 100.0 * 3600.0);
+    assert_ne!(then.pos, now.pos);
+
+    solar_system.restore_save(3).unwrap();
+    assert_eq!(solar_system.get_time(), 30.0 * 3600.0);
+    assert_eq!(solar_system.list_saves().len(), 4);
+    // Saves in between keyframes are only stored to f32 precision
+    let error = (earth(solar_system.get_orbiters()).pos - then.pos).length();
+    assert!(error < 1.0, "restored Earth is {} m off", error);
+
+    // Carrying on saves again without doubling up on the one it went back to
+    for _ in 0..20 {
+        solar_system.update(3600.0);
+    }
+    let steps: Vec<usize> = solar_system
+        .list_saves()
+        .iter()
+        .map(|save| save.step)
+        .collect();
+    assert_eq!(steps, vec![0, 10, 20, 30, 40]);
+}
+
+#[test]
+fn going_back_keeps_the_old_timeline_as_a_branch() {
+    let mut solar_system = sun_and_earth(HistoryPolicy {
+        save_every: 10,
+        ..HistoryPolicy::default()
+    });
+    for _ in 0..100 {
+        solar_system.update(3600.0);
+    }
+    solar_system.restore_save(5).unwrap();
+    // Take a different path this time
+    for _ in 0..50 {
+        solar_system.update(7200.0);
+    }
+
+    let branches = solar_system.list_branches();
+    assert_eq!(branches.len(), 2);
+    assert_eq!((branches[0].step, branches[0].current), (100, false));
+    assert_eq!(branches[1].parent, Some(0));
+    assert_eq!(branches[1].fork_step, 50);
+    assert_eq!((branches[1].step, branches[1].current), (100, true));
+
+    let diff = solar_system.diff_branches(0, 1).unwrap();
+    assert_eq!(diff.time_difference, 50.0 * 3600.0);
+    assert!(diff.only_in_first.is_empty() && diff.only_in_second.is_empty());
+    let earth = diff
+        .bodies
+        .iter()
+        .find(|body| body.name == "Earth")
+        .unwrap();
+    assert!(earth.offset.length() > 1e9);
+
+    // Both branches carry on from where they were left
+    let ahead = solar_system.get_orbiters();
+    solar_system.switch_branch(0).unwrap();
+    assert_eq!(solar_system.get_time(), 100.0 * 3600.0);
+    assert_eq!(solar_system.list_saves().len(), 10);
+    solar_system.switch_branch(1).unwrap();
+    assert_eq!(solar_system.get_time(), 150.0 * 3600.0);
+    let positions = |orbiters: BTreeMap<BodyId, Orbiter>| -> Vec<_> {
+        orbiters.values().map(|orbiter| orbiter.1.pos).collect()
+    };
+    assert_eq!(positions(solar_system.get_orbiters()), positions(ahead));
+
+    // Going back to where things already are doesn't need a new branch
+    solar_system.save();
+    let newest = solar_system.list_saves().len() - 1;
+    solar_system.restore_save(newest).unwrap();
+    assert_eq!(solar_system.list_branches().len(), 2);
+    assert_eq!(
+        solar_system.switch_branch(2),
+        Err(HistoryError::NoSuchBranch { index: 2, count: 2 })
+    );
+}
+
+#[test]
+fn in_between_saves_is_interpolated_smoothly() {
+    let history = HistoryPolicy {
+        save_every: 10,
+        ..HistoryPolicy::default()
+    };
+    let mut solar_system = sun_and_earth(history);
+    for _ in 0..100 {
+        solar_system.update(3600.0);
+    }
+    let mut actual = sun_and_earth(history);
+    for _ in 0..35 {
+        actual.update(3600.0);
+    }
+
+    let earth = |orbiters: BTreeMap<BodyId, Orbiter>| orbiters.values().nth(1).unwrap().1;
+    let guess = earth(solar_system.state_at(35.0 * 3600.0).unwrap());
+    let actual = earth(actual.get_orbiters());
+    // Cutting straight across would be about a thousand kilometers off
+    let error = (guess.pos - actual.pos).length();
+    assert!(error < 1e4, "interpolated Earth is {} m off", error);
+    assert!((guess.vel - actual.vel).length() < 0.1);
+
+    // The ends are exact
+    assert_eq!(
+        earth(solar_system.state_at(100.0 * 3600.0).unwrap()).pos,
+        earth(solar_system.get_orbiters()).pos
+    );
+    assert_eq!(
+        solar_system.state_at(-1.0).unwrap_err(),
+        HistoryError::OutsideHistory {
+            time: -1.0,
+            earliest: 0.0,
+            latest: 100.0 * 3600.0
+        }
+    );
+}
+
+#[test]
+fn far_apart_saves_get_simulated_again_instead() {
+    // Saves are about a ninth of a year apart, which is way too far to interpolate across
+    let history = HistoryPolicy {
+        save_every: 1000,
+        keyframe_every: 1,
+        ..HistoryPolicy::default()
+    };
+    let mut solar_system = sun_and_earth(history);
+    for _ in 0..2000 {
+        solar_system.update(3600.0);
+    }
+    let earth = |orbiters: BTreeMap<BodyId, Orbiter>| orbiters.values().nth(1).unwrap().1;
+    for &hours in &[300, 1700] {
+        let mut actual = sun_and_earth(history);
+        for _ in 0..hours {
+            actual.update(3600.0);
+        }
+        // Same steps from the same place, so it's the same answer
+        let guess = earth(solar_system.state_at(hours as f64 * 3600.0).unwrap());
+        let actual = earth(actual.get_orbiters());
+        let error = (guess.pos - actual.pos).length();
+        assert!(error < 1.0, "{} hours in, Earth is {} m off", hours, error);
+    }
+}
+
+#[test]
+fn missing_saves_are_errors() {
+    let mut solar_system = sun_and_earth(HistoryPolicy::default());
+    assert_eq!(
+        solar_system.restore_save(0),
+        Err(HistoryError::NoSuchSave { index: 0, count: 0 })
+    );
+    solar_system.update(60.0);
+    assert!(solar_system.preview_save(1).is_err());
+    assert!(solar_system.discard_saves_after(5).is_err());
+    assert!(solar_system.preview_save(0).is_ok());
+}
+
+#[test]
+fn old_history_gets_thinned_instead_of_dropped() {
+    let mut solar_system = sun_and_earth(HistoryPolicy {
+        save_every: 1,
+        save_count: 20,
+        ..HistoryPolicy::default()
+    });
+    for _ in 0..1000 {
+        solar_system.update(60.0);
+    }
+    let steps: Vec<usize> = solar_system
+        .list_saves()
+        .iter()
+        .map(|save| save.step)
+        .collect();
+    assert_eq!(steps.len(), 20);
+    // The very start is still there, and the newest ones are packed tightly
+    assert_eq!(steps[0], 0);
+    assert_eq!(steps[19], 999);
+    assert!(steps[19] - steps[18] < steps[2] - steps[1]);
+}
+
+#[test]
+fn adaptive_steps_save_by_simulated_time() {
+    let day = 86_400.0;
+    let mut solar_system = sun_and_earth(HistoryPolicy {
+        save_every_seconds: 10.0 * day,
+        ..HistoryPolicy::default()
+    });
+    let mut steps = 0;
+    for _ in 0..200 {
+        steps += solar_system.update_adaptive(day / 2.0, 0.001);
+    }
+    // Lots of steps, but still a save every ten days however long each call is
+    assert!(steps > 1000, "{} steps", steps);
+    let saves = solar_system.list_saves();
+    assert_eq!(saves.len(), 10);
+    for (n, save) in saves.iter().enumerate() {
+        let expected = n as f64 * 10.0 * day;
+        assert!((save.time - expected).abs() < 1.0, "{}", save.time);
+    }
+}
+
+#[test]
+fn thinned_out_saves_dont_keep_keyframes_they_dont_need() {
+    let policy = |keyframe_every| HistoryPolicy {
+        save_every: 1,
+        save_count: 10,
+        keyframe_every,
+        ..HistoryPolicy::default()
+    };
+    let mut grouped = sun_and_earth(policy(4));
+    let mut all_keyframes = sun_and_earth(policy(1));
+    for _ in 0..1000 {
+        grouped.update(60.0);
+        all_keyframes.update(60.0);
+    }
+    assert_eq!(grouped.list_saves(), all_keyframes.list_saves());
+    // Old saves end up alone in their segments, and those become keyframes themselves
+    // instead of being stored on top of one that's only there for them
+    assert!(
+        grouped.history_bytes() <= all_keyframes.history_bytes(),
+        "{} vs {}",
+        grouped.history_bytes(),
+        all_keyframes.history_bytes()
+    );
+}
+
+#[test]
+fn changing_a_body_only_changes_the_current_branch() {
+    let mut solar_system = sun_and_earth(HistoryPolicy {
+        save_every: 10,
+        ..HistoryPolicy::default()
+    });
+    for _ in 0..100 {
+        solar_system.update(3600.0);
+    }
+    solar_system.restore_save(5).unwrap();
+    let ids: Vec<BodyId> = solar_system.get_orbiters().keys().copied().collect();
+    // What if the Earth were as heavy as the Sun?
+    let heavy_earth = Body {
+        mass: 2e30,
+        ..solar_system.get_orbiter(ids[1]).unwrap().0
+    };
+    let before = solar_system.get_orbiter(ids[1]).unwrap().1;
+    let new_earth = solar_system.set_body(ids[1], heavy_earth).unwrap();
+    assert!(solar_system.get_orbiter(ids[1]).is_none());
+    let after = solar_system.get_orbiter(new_earth).unwrap();
+    assert_eq!(after.0.mass, 2e30);
+    assert_eq!((after.1.pos, after.1.vel), (before.pos, before.vel));
+    for _ in 0..50 {
+        solar_system.update(3600.0);
+    }
+
+    // The Sun gets pulled way off course this time
+    let diff = solar_system.diff_branches(0, 1).unwrap();
+    assert_eq!(diff.time_difference, 0.0);
+    assert_eq!(diff.only_in_first[0].0, ids[1]);
+    assert_eq!(diff.only_in_second[0].0, new_earth);
+    let sun = diff.bodies.iter().find(|body| body.name == "Sun").unwrap();
+    assert!(sun.offset.length() > 1e7, "{}", sun.offset.length());
+
+    // Everything that already happened still has the old Earth
+    let start = solar_system.preview_save(0).unwrap();
+    assert_eq!(start[&ids[1]].0.mass, 6e24);
+    solar_system.switch_branch(0).unwrap();
+    assert_eq!(solar_system.get_orbiter(ids[1]).unwrap().0.mass, 6e24);
+    assert_eq!(
+        solar_system.set_body(new_earth, start[&ids[0]].0.clone()),
+        None
+    );
+}
@@ -0,0 +1,98 @@
+mod common;
+
+use common::body;
+use euclid::default::{Point2D, Vector2D};
+use simulator::bodies::{Body, Kinemat, Orbiter};
+use simulator::config::SimulationConfig;
+use simulator::{integrator, SolarSystem, GRAV_CONSTANT};
+use std::f64::consts::PI;
+
+const SUN_MASS: f64 = 2e30;
+const DIST: f64 = 1.5e11;
+
+/// Go `orbits` times around an immovable Sun in a circle with `integrator`, taking `steps` steps each time.
+/// Returns the most the energy ever drifted, and how far from the start it ended up.
+fn orbit(integrator: &str, steps: usize, orbits: usize) -> (f64, f64) {
+    let mu = GRAV_CONSTANT * SUN_MASS;
+    let speed = (mu / DIST).sqrt();
+    let start = Point2D::new(DIST, 0.0);
+    let orbiters = vec![
+        Orbiter(
+            Body {
+                immovable: true,
+                ..body("Sun", SUN_MASS, 1.0)
+            },
+            Kinemat::zero(),
+        ),
+        Orbiter(
+            body("Planet", 1.0, 1.0),
+            Kinemat::new(start, Vector2D::new(0.0, speed)),
+        ),
+    ];
+    let mut solar_system = SolarSystem::new(
+        orbiters,
+        SimulationConfig::default(),
+        integrator::from_name(integrator).unwrap(),
+    );
+
+    // The Sun never moves, so the planet's energy per kilogram is all there is
+    let planet =
+        |solar_system: &SolarSystem| solar_system.get_orbiters().values().nth(1).unwrap().1;
+    let energy = |k: Kinemat| k.vel.square_length() / 2.0 - mu / k.pos.to_vector().length();
+    let initial = energy(planet(&solar_system));
+
+    let period = 2.0 * PI * DIST / speed;
+    let mut worst_drift: f64 = 0.0;
+    for _ in 0..steps * orbits {
+        solar_system.update(period / steps as f64);
+        let drift = (energy(planet(&solar_system)) - initial) / initial.abs();
+        worst_drift = worst_drift.max(drift.abs());
+    }
+    let end = planet(&solar_system).pos;
+    (worst_drift, (end - start).length())
+}
+
+#[test]
+fn symplectic_integrators_keep_energy_steady() {
+    let (euler, _) = orbit("euler", 365, 1);
+    let (leapfrog, _) = orbit("leapfrog", 365, 1);
+    let (yoshida4, _) = orbit("yoshida4", 365, 1);
+    assert!(
+        leapfrog < 1e-3 && yoshida4 < 1e-3,
+        "{} {}",
+        leapfrog,
+        yoshida4
+    );
+    assert!(
+        leapfrog < euler / 10.0,
+        "leapfrog {} vs euler {}",
+        leapfrog,
+        euler
+    );
+    assert!(
+        yoshida4 < leapfrog / 10.0,
+        "yoshida4 {} vs leapfrog {}",
+        yoshida4,
+        leapfrog
+    );
+
+    // Bounded, not just small: going around ten times as long doesn't make it any worse
+    let (leapfrog_long, _) = orbit("leapfrog", 365, 10);
+    assert!(
+        leapfrog_long < leapfrog * 1.1,
+        "{} after ten orbits",
+        leapfrog_long
+    );
+}
+
+#[test]
+fn rk4_is_fourth_order() {
+    let (_, coarse) = orbit("rk4", 100, 1);
+    let (_, fine) = orbit("rk4", 200, 1);
+    let ratio = coarse / fine;
+    assert!(
+        (13.0..19.0).contains(&ratio),
+        "halving dt cut the error by {}",
+        ratio
+    );
+}
@@ -0,0 +1,179 @@
+mod common;
+
+use common::body;
+use euclid::default::{Point2D, Vector2D};
+use simulator::bodies::{Kinemat, Orbiter};
+use simulator::config::SimulationConfig;
+use simulator::integrator::Leapfrog;
+use simulator::orbit::OrbitalElements;
+use simulator::{SolarSystem, GRAV_CONSTANT};
+use std::f64::consts::PI;
+
+const SUN_MU: f64 = GRAV_CONSTANT * 2e30;
+
+/// Somewhere the Sun isn't, moving
+fn sun() -> Kinemat {
+    Kinemat::new(Point2D::new(3e10, -1e10), Vector2D::new(2000.0, 500.0))
+}
+
+fn assert_close(a: f64, b: f64, what: &str) {
+    assert!(
+        (a - b).abs() <= 1e-9 * a.abs().max(b.abs()).max(1.0),
+        "{}: {} isn't {}",
+        what,
+        a,
+        b
+    );
+}
+
+fn assert_round_trips(kmat: Kinemat) -> OrbitalElements {
+    let elements = OrbitalElements::from_kinemat(kmat, sun(), SUN_MU);
+    let back = elements.to_kinemat(sun());
+    let pos_error = (back.pos - kmat.pos).length() / (kmat.pos - sun().pos).length();
+    let vel_error = (back.vel - kmat.vel).length() / (kmat.vel - sun().vel).length();
+    assert!(
+        pos_error < 1e-12 && vel_error < 1e-12,
+        "{:?} came back as {:?} via {:?}",
+        kmat,
+        back,
+        elements
+    );
+    elements
+}
+
+/// Something `dist` meters from the Sun, going `speed` m/s at `angle` radians from straight outwards
+fn launched(dist: f64, speed: f64, angle: f64, direction: f64) -> Kinemat {
+    let sun = sun();
+    let out = Vector2D::new(direction.cos(), direction.sin());
+    let vel = Vector2D::new(
+        out.x * angle.cos() - out.y * angle.sin(),
+        out.x * angle.sin() + out.y * angle.cos(),
+    ) * speed;
+    Kinemat::new(sun.pos + out * dist, sun.vel + vel)
+}
+
+#[test]
+fn all_kinds_of_orbits_round_trip() {
+    let earth_speed = (SUN_MU / 1.5e11).sqrt();
+    for &direction in &[0.0, 1.0, 2.5, 4.0, 5.9] {
+        for &speed in &[0.3, 0.9, 1.0, 1.2, 1.5, 3.0] {
+            // Outwards, sideways both ways, and inwards
+            for &angle in &[0.4, PI / 2.0, -PI / 2.0, 2.0, -2.9] {
+                assert_round_trips(launched(1.5e11, speed * earth_speed, angle, direction));
+            }
+        }
+    }
+}
+
+#[test]
+fn earth_like_orbit() {
+    let speed = (SUN_MU / 1.5e11).sqrt();
+    let elements = assert_round_trips(launched(1.5e11, speed, PI / 2.0, 0.3));
+    assert!(elements.eccentricity < 1e-9);
+    assert_close(elements.semi_major_axis, 1.5e11, "semi-major axis");
+    assert_close(elements.periapsis(), 1.5e11, "periapsis");
+    assert_close(elements.apoapsis().unwrap(), 1.5e11, "apoapsis");
+    let year = 2.0 * PI * (1.5e11f64.powi(3) / SUN_MU).sqrt();
+    assert_close(elements.period().unwrap(), year, "period");
+    assert!(!elements.clockwise);
+}
+
+#[test]
+fn elliptical_orbit() {
+    // Half of circular speed going sideways means it's at apoapsis of a skinny ellipse
+    let speed = 0.5 * (SUN_MU / 2e11).sqrt();
+    let elements = assert_round_trips(launched(2e11, speed, -PI / 2.0, PI / 3.0));
+    assert!(elements.clockwise);
+    assert_close(elements.apoapsis().unwrap(), 2e11, "apoapsis");
+    // Energy says a = r / (2 - r v^2 / mu)
+    assert_close(elements.semi_major_axis, 2e11 / 1.75, "semi-major axis");
+    assert_close(elements.eccentricity, 0.75, "eccentricity");
+    assert_close(elements.periapsis(), 2e11 / 1.75 * 0.25, "periapsis");
+    // Periapsis is straight across from here
+    assert_close(
+        elements.argument_of_periapsis,
+        PI / 3.0 + PI,
+        "argument of periapsis",
+    );
+    assert_close(elements.true_anomaly, PI, "true anomaly");
+    assert_close(elements.mean_anomaly, PI, "mean anomaly");
+}
+
+#[test]
+fn escaping_orbit() {
+    // Heading outwards at well over escape speed
+    let speed = 1.5 * (2.0 * SUN_MU / 1e11).sqrt();
+    let elements = assert_round_trips(launched(1e11, speed, 1.0, 0.0));
+    assert!(!elements.is_bound());
+    assert!(elements.semi_major_axis < 0.0);
+    assert_eq!(elements.period(), None);
+    assert_eq!(elements.apoapsis(), None);
+    // It's on the way out, so it's past periapsis
+    assert!(elements.true_anomaly > 0.0 && elements.mean_anomaly > 0.0);
+    let incoming = assert_round_trips(launched(1e11, speed, PI - 1.0, 0.0));
+    assert!(incoming.true_anomaly < 0.0 && incoming.mean_anomaly < 0.0);
+}
+
+#[test]
+fn mean_anomaly_goes_up_steadily() {
+    let speed = 0.8 * (SUN_MU / 1.5e11).sqrt();
+    let orbiters = vec![
+        Orbiter(body("Sun", 2e30, 1.0), Kinemat::zero()),
+        Orbiter(
+            body("Comet", 1.0, 1.0),
+            Kinemat::new(Point2D::new(1.5e11, 0.0), Vector2D::new(0.0, speed)),
+        ),
+    ];
+    let mut solar_system =
+        SolarSystem::new(orbiters, SimulationConfig::default(), Box::new(Leapfrog));
+    let ids: Vec<_> = solar_system.get_orbiters().keys().copied().collect();
+    let before = solar_system.get_orbital_elements(ids[1], ids[0]).unwrap();
+    for _ in 0..1000 {
+        solar_system.update(600.0);
+    }
+    let after = solar_system.get_orbital_elements(ids[1], ids[0]).unwrap();
+    let expected = before.mean_anomaly + before.mean_motion() * 600_000.0;
+    assert!((after.mean_anomaly - expected).abs() < 1e-4);
+    assert!((after.semi_major_axis - before.semi_major_axis).abs() < 1e-6 * before.semi_major_axis);
+}
+
+#[test]
+fn parabolic_orbit() {
+    // Exactly parabolic, at periapsis 1 AU out
+    let p = 2.0 * 1.5e11;
+    let elements = OrbitalElements {
+        semi_major_axis: f64::INFINITY,
+        eccentricity: 1.0,
+        semi_latus_rectum: p,
+        argument_of_periapsis: 0.0,
+        true_anomaly: 0.0,
+        mean_anomaly: 0.0,
+        clockwise: false,
+        gravitational_parameter: SUN_MU,
+    };
+    assert_close(elements.periapsis(), 1.5e11, "periapsis");
+    assert_eq!(elements.period(), None);
+    assert_close(
+        elements.mean_motion(),
+        (SUN_MU / p.powi(3)).sqrt(),
+        "mean motion",
+    );
+
+    // Barker's equation says it's a quarter of the way around after this long
+    let quarter = 2.0 / 3.0 * (p.powi(3) / SUN_MU).sqrt();
+    let later = elements.advanced(quarter);
+    assert_close(later.true_anomaly, PI / 2.0, "true anomaly");
+    let kmat = later.to_kinemat(sun());
+    assert_close((kmat.pos - sun().pos).length(), p, "distance");
+    // Escape speed, since it has exactly none to spare
+    assert_close(
+        (kmat.vel - sun().vel).length(),
+        (2.0 * SUN_MU / p).sqrt(),
+        "speed",
+    );
+
+    // And it works out the same orbit from there, give or take rounding
+    let back = OrbitalElements::from_kinemat(kmat, sun(), SUN_MU);
+    assert_close(back.semi_latus_rectum, p, "semi-latus rectum");
+    assert!((back.eccentricity - 1.0).abs() < 1e-12);
+}
@@ -0,0 +1,107 @@
+mod common;
+
+use common::body;
+use euclid::default::{Point2D, Vector2D};
+use simulator::bodies::{Kinemat, Orbiter};
+use simulator::config::SimulationConfig;
+use simulator::integrator::Yoshida4;
+use simulator::{BodyId, SolarSystem, Trajectory};
+
+const HOUR: f64 = 3600.0;
+
+/// The Sun, the Earth, the Moon, and a dust cloud headed straight for the Earth
+fn system() -> (SolarSystem, Vec<BodyId>) {
+    let orbiters = vec![
+        Orbiter(body("Sun", 2e30, 7e8), Kinemat::zero()),
+        Orbiter(
+            body("Earth", 6e24, 6.4e6),
+            Kinemat::new(Point2D::new(1.5e11, 0.0), Vector2D::new(0.0, 29780.0)),
+        ),
+        Orbiter(
+            body("Moon", 7.3e22, 1.7e6),
+            Kinemat::new(
+                Point2D::new(1.5e11 + 3.84e8, 0.0),
+                Vector2D::new(0.0, 29780.0 + 1022.0),
+            ),
+        ),
+        Orbiter(
+            body("Dust cloud", 1e12, 2e7),
+            Kinemat::new(Point2D::new(1.5e11 - 1e8, 0.0), Vector2D::new(1e4, 29780.0)),
+        ),
+    ];
+    let solar_system = SolarSystem::new(orbiters, SimulationConfig::default(), Box::new(Yoshida4));
+    let ids = solar_system.get_orbiters().keys().copied().collect();
+    (solar_system, ids)
+}
+
+#[test]
+fn predictions_come_true() {
+    let (mut solar_system, ids) = system();
+    let before = solar_system.get_orbiters();
+    let prediction = solar_system.predict(240.0 * HOUR, HOUR, 24, None);
+
+    // Nothing actually happened
+    assert_eq!(solar_system.get_time(), 0.0);
+    assert!(solar_system.list_saves().is_empty());
+    for (id, orbiter) in solar_system.get_orbiters() {
+        assert_eq!(orbiter.1.pos, before[&id].1.pos);
+    }
+
+    // The start, then every day
+    let moon = &prediction[&ids[2]];
+    let times: Vec<f64> = moon.samples.iter().map(|&(time, _)| time).collect();
+    let expected: Vec<f64> = (0..=10).map(|day| day as f64 * 24.0 * HOUR).collect();
+    assert_eq!(times, expected);
+    assert_eq!(moon.ended, None);
+
+    // The dust cloud hits the Earth, and what they merge into takes over
+    let cloud = &prediction[&ids[3]];
+    let impact = cloud.ended.unwrap();
+    assert_eq!(prediction[&ids[1]].ended, Some(impact));
+    assert_eq!(prediction.len(), 5);
+    let merged = prediction.values().last().unwrap();
+    assert!(merged.samples[0].0 > impact);
+
+    // Running it for real goes exactly the same way
+    for _ in 0..240 {
+        solar_system.update(HOUR);
+    }
+    let moon_now = solar_system.get_orbiter(ids[2]).unwrap().1;
+    let (_, moon_predicted) = *moon.samples.last().unwrap();
+    assert_eq!(moon_now.pos, moon_predicted.pos);
+    assert_eq!(moon_now.vel, moon_predicted.vel);
+    assert!(solar_system.get_orbiter(ids[3]).is_none());
+}
+
+#[test]
+fn predicting_only_some_things() {
+    let (solar_system, ids) = system();
+    let prediction = solar_system.predict(-10.0 * HOUR, 4.0 * HOUR, 1, Some(&ids[2..3]));
+    assert_eq!(prediction.keys().copied().collect::<Vec<_>>(), vec![ids[2]]);
+    // Backwards, with a short step at the end to land right on the end
+    let times: Vec<f64> = prediction[&ids[2]]
+        .samples
+        .iter()
+        .map(|&(time, _)| time)
+        .collect();
+    assert_eq!(times, vec![0.0, -4.0 * HOUR, -8.0 * HOUR, -10.0 * HOUR]);
+}
+
+#[test]
+fn predicting_on_another_thread() {
+    let (solar_system, ids) = system();
+    let moon = ids[2];
+    let here = solar_system.predict(10.0 * HOUR, HOUR, 1, Some(&[moon]));
+    let there =
+        std::thread::spawn(move || solar_system.predict(10.0 * HOUR, HOUR, 1, Some(&[moon])))
+            .join()
+            .unwrap();
+    let positions = |trajectory: &Trajectory| -> Vec<_> {
+        trajectory
+            .samples
+            .iter()
+            .map(|(_, kmat)| kmat.pos)
+            .collect()
+    };
+    assert_eq!(positions(&here[&moon]), positions(&there[&moon]));
+}
@@ -0,0 +1,171 @@
+mod common;
+
+use common::body;
+use euclid::default::{Point2D, Vector2D};
+use simulator::bodies::{Body, Kinemat, Orbiter};
+use simulator::config::SimulationConfig;
+use simulator::integrator::Leapfrog;
+use simulator::orbit::OrbitalElements;
+use simulator::{BodyId, Propagation, RailsError, SolarSystem, GRAV_CONSTANT};
+
+const AU: f64 = 1.496e11;
+const DAY: f64 = 86_400.0;
+
+/// The Sun, Jupiter, a comet on an eccentric orbit, and the comet's tiny moon
+fn system(config: SimulationConfig) -> (SolarSystem, Vec<BodyId>) {
+    let sun_mu = GRAV_CONSTANT * 2e30;
+    let comet = Kinemat::new(
+        Point2D::new(AU, 0.0),
+        Vector2D::new(0.0, 1.3 * (sun_mu / AU).sqrt()),
+    );
+    let moon_dist = 5e6;
+    let moon = Kinemat::new(
+        comet.pos + Vector2D::new(moon_dist, 0.0),
+        comet.vel + Vector2D::new(0.0, (GRAV_CONSTANT * 1e20 / moon_dist).sqrt()),
+    );
+    let orbiters = vec![
+        Orbiter(body("Sun", 2e30, 1.0), Kinemat::zero()),
+        Orbiter(
+            body("Jupiter", 1.9e27, 1.0),
+            Kinemat::new(
+                Point2D::new(0.0, 5.2 * AU),
+                Vector2D::new(-(sun_mu / (5.2 * AU)).sqrt(), 0.0),
+            ),
+        ),
+        Orbiter(body("Comet", 1e20, 1.0), comet),
+        Orbiter(body("Moon", 1e10, 1.0), moon),
+        Orbiter(
+            Body {
+                immovable: true,
+                ..body("Beacon", 1.0, 1.0)
+            },
+            Kinemat::new(Point2D::new(-10.0 * AU, 0.0), Vector2D::zero()),
+        ),
+    ];
+    let solar_system = SolarSystem::new(orbiters, config, Box::new(Leapfrog));
+    let ids = solar_system.get_orbiters().keys().copied().collect();
+    (solar_system, ids)
+}
+
+/// The comet's orbit around the Sun
+fn comet_orbit(solar_system: &SolarSystem, ids: &[BodyId]) -> OrbitalElements {
+    solar_system.get_orbital_elements(ids[2], ids[0]).unwrap()
+}
+
+#[test]
+fn rails_follow_kepler_exactly() {
+    let (mut solar_system, ids) = system(SimulationConfig::default());
+    solar_system
+        .set_propagation(ids[2], Propagation::OnRails { primary: ids[0] })
+        .unwrap();
+    let start = comet_orbit(&solar_system, &ids);
+    // Steps this big would wreck an integrated orbit this eccentric
+    for _ in 0..100 {
+        solar_system.update(10.0 * DAY);
+    }
+
+    // Jupiter doesn't get a say, so the orbit is exactly where Kepler says it should be
+    let sun = solar_system.get_orbiter(ids[0]).unwrap().1;
+    let expected = start.advanced(1000.0 * DAY).to_kinemat(sun);
+    let actual = solar_system.get_orbiter(ids[2]).unwrap().1;
+    let error = (actual.pos - expected.pos).length();
+    assert!(error < 1e-6 * AU, "comet is {} m off", error);
+    let end = comet_orbit(&solar_system, &ids);
+    assert!((end.semi_major_axis - start.semi_major_axis).abs() < 1e-9 * start.semi_major_axis);
+    assert!((end.eccentricity - start.eccentricity).abs() < 1e-9);
+
+    // Taking it off the rails puts it back under everything's gravity
+    solar_system
+        .set_propagation(ids[2], Propagation::Integrated)
+        .unwrap();
+    assert_eq!(
+        solar_system.get_propagation(ids[2]),
+        Some(Propagation::Integrated)
+    );
+    for _ in 0..100 {
+        solar_system.update(DAY);
+    }
+    let perturbed = comet_orbit(&solar_system, &ids);
+    assert!((perturbed.semi_major_axis - end.semi_major_axis).abs() > 1e-6 * end.semi_major_axis);
+}
+
+#[test]
+fn light_things_start_on_rails_around_their_primaries() {
+    let config = SimulationConfig::builder()
+        .on_rails_below(Some(1e21))
+        .build();
+    let (mut solar_system, ids) = system(config);
+    // The moon goes around the comet, which goes around the Sun. The immovable beacon stays put
+    assert_eq!(
+        solar_system.get_propagation(ids[2]),
+        Some(Propagation::OnRails { primary: ids[0] })
+    );
+    assert_eq!(
+        solar_system.get_propagation(ids[3]),
+        Some(Propagation::OnRails { primary: ids[2] })
+    );
+    assert_eq!(
+        solar_system.get_propagation(ids[4]),
+        Some(Propagation::Integrated)
+    );
+
+    for _ in 0..1000 {
+        solar_system.update(DAY / 4.0);
+    }
+    // The moon kept up with the comet even though it's on rails around something on rails
+    let comet = solar_system.get_orbiter(ids[2]).unwrap().1;
+    let moon = solar_system.get_orbiter(ids[3]).unwrap().1;
+    assert!(((moon.pos - comet.pos).length() - 5e6).abs() < 1.0);
+}
+
+#[test]
+fn bad_rails_are_errors() {
+    let (mut solar_system, ids) = system(SimulationConfig::default());
+    let rails = |primary| Propagation::OnRails { primary };
+    solar_system.set_propagation(ids[3], rails(ids[2])).unwrap();
+    assert_eq!(
+        solar_system.set_propagation(ids[2], rails(ids[3])),
+        Err(RailsError::Loop {
+            id: ids[2],
+            primary: ids[3]
+        })
+    );
+    assert_eq!(
+        solar_system.set_propagation(ids[2], rails(ids[2])),
+        Err(RailsError::Loop {
+            id: ids[2],
+            primary: ids[2]
+        })
+    );
+    assert_eq!(
+        solar_system.set_propagation(ids[4], rails(ids[0])),
+        Err(RailsError::Immovable(ids[4]))
+    );
+}
+
+#[test]
+fn every_branch_keeps_its_own_rails() {
+    let (mut solar_system, ids) = system(SimulationConfig::default());
+    let on_rails = Propagation::OnRails { primary: ids[0] };
+    solar_system.set_propagation(ids[2], on_rails).unwrap();
+    for _ in 0..10 {
+        solar_system.update(DAY);
+    }
+
+    // The new branch starts out with the same rails
+    solar_system.restore_save(0).unwrap();
+    assert_eq!(solar_system.current_branch(), 1);
+    assert_eq!(solar_system.get_propagation(ids[2]), Some(on_rails));
+    solar_system
+        .set_propagation(ids[2], Propagation::Integrated)
+        .unwrap();
+
+    // But taking something off them there doesn't take it off them anywhere else
+    solar_system.switch_branch(0).unwrap();
+    assert_eq!(solar_system.get_propagation(ids[2]), Some(on_rails));
+    solar_system.switch_branch(1).unwrap();
+    assert_eq!(
+        solar_system.get_propagation(ids[2]),
+        Some(Propagation::Integrated)
+    );
+}
@@ -0,0 +1,79 @@
+mod common;
+
+use common::body;
+use euclid::default::{Point2D, Vector2D};
+use simulator::bodies::{Kinemat, Orbiter};
+use simulator::config::{HistoryPolicy, SimulationConfig};
+use simulator::integrator::RungeKutta4;
+use simulator::{SnapshotError, SolarSystem};
+
+/// A star, a planet, and a moon that's about to fall into the planet
+fn small_system() -> SolarSystem {
+    let orbiters = vec![
+        Orbiter(body("Star", 2e30, 7e8), Kinemat::zero()),
+        Orbiter(
+            body("Planet", 6e24, 6.4e6),
+            Kinemat::new(Point2D::new(1.5e11, 0.0), Vector2D::new(0.0, 29780.0)),
+        ),
+        Orbiter(
+            body("Moon", 7e22, 1.7e6),
+            Kinemat::new(Point2D::new(1.5e11, 4e7), Vector2D::new(0.0, 29780.0)),
+        ),
+    ];
+    let config = SimulationConfig::builder()
+        .history(HistoryPolicy {
+            save_every: 10,
+            save_count: 100,
+            ..HistoryPolicy::default()
+        })
+        .build();
+    SolarSystem::new(orbiters, config, Box::new(RungeKutta4))
+}
+
+#[test]
+fn snapshot_picks_up_where_it_left_off() {
+    let mut original = small_system();
+    for _ in 0..500 {
+        original.update(60.0);
+    }
+    assert_eq!(original.get_orbiters().len(), 2, "the moon should have hit");
+
+    let mut bytes = Vec::new();
+    original.write_snapshot(&mut bytes).unwrap();
+    let mut restored = SolarSystem::read_snapshot(&bytes[..]).unwrap();
+    assert_eq!(restored.get_time(), original.get_time());
+    assert_eq!(restored.get_integrator_name(), "rk4");
+    assert_eq!(restored.get_config(), original.get_config());
+
+    for _ in 0..500 {
+        original.update(60.0);
+        restored.update(60.0);
+    }
+    let (original, restored) = (original.get_orbiters(), restored.get_orbiters());
+    assert_eq!(
+        original.keys().collect::<Vec<_>>(),
+        restored.keys().collect::<Vec<_>>()
+    );
+    for (a, b) in original.values().zip(restored.values()) {
+        assert_eq!(a.0.name, b.0.name);
+        assert_eq!(a.1.pos, b.1.pos);
+        assert_eq!(a.1.vel, b.1.vel);
+    }
+}
+
+#[test]
+fn snapshot_rejects_other_files() {
+    let not_a_snapshot = b"[ { body: \"sol\" } ]";
+    match SolarSystem::read_snapshot(&not_a_snapshot[..]) {
+        Err(SnapshotError::NotASnapshot) => {}
+        other => panic!("expected NotASnapshot, got {:?}", other.err()),
+    }
+
+    let mut bytes = Vec::new();
+    small_system().write_snapshot(&mut bytes).unwrap();
+    bytes[8] = 99;
+    match SolarSystem::read_snapshot(&bytes[..]) {
+        Err(SnapshotError::UnsupportedVersion(99)) => {}
+        other => panic!("expected UnsupportedVersion, got {:?}", other.err()),
+    }
+}
@@ -0,0 +1,90 @@
+mod common;
+
+use common::body;
+use euclid::default::{Point2D, Vector2D};
+use simulator::bodies::{Kinemat, Orbiter};
+use simulator::config::{SimulationConfig, Softening};
+use simulator::gravity::{self, GravitySolver};
+use simulator::integrator::Leapfrog;
+use simulator::SolarSystem;
+
+const LENGTH: f64 = 1e7;
+
+/// How hard a 1e24 kg planet pulls on something `dist` meters away
+fn pull(softening: Softening, dist: f64) -> f64 {
+    let config = SimulationConfig::builder().softening(softening).build();
+    let pos = [Point2D::zero(), Point2D::new(dist, 0.0)];
+    let mut acc = [Vector2D::zero(); 2];
+    gravity::accelerations(
+        GravitySolver::Direct,
+        &config,
+        &pos,
+        &[1e24, 1.0],
+        &[false, false],
+        &mut acc,
+    );
+    -acc[1].x
+}
+
+/// The potential energy of the same two things
+fn potential(softening: Softening, dist: f64) -> f64 {
+    let body = |mass: f64| body("", mass, 1.0);
+    let orbiters = vec![
+        Orbiter(body(1e24), Kinemat::zero()),
+        Orbiter(
+            body(1.0),
+            Kinemat::new(Point2D::new(dist, 0.0), Vector2D::zero()),
+        ),
+    ];
+    let config = SimulationConfig::builder().softening(softening).build();
+    SolarSystem::new(orbiters, config, Box::new(Leapfrog)).potential_energy()
+}
+
+#[test]
+fn forces_match_potentials() {
+    for &softening in &[
+        Softening::None,
+        Softening::Plummer { length: LENGTH },
+        Softening::Spline { length: LENGTH },
+    ] {
+        for &dist in &[
+            0.1 * LENGTH,
+            0.4 * LENGTH,
+            0.6 * LENGTH,
+            0.9 * LENGTH,
+            3.0 * LENGTH,
+        ] {
+            // The force is how fast the potential changes
+            let h = dist * 1e-4;
+            let slope =
+                (potential(softening, dist + h) - potential(softening, dist - h)) / (2.0 * h);
+            let force = pull(softening, dist);
+            assert!(
+                (slope - force).abs() < 1e-6 * force,
+                "{:?} at {}: force {} but slope {}",
+                softening,
+                dist,
+                force,
+                slope
+            );
+        }
+    }
+}
+
+#[test]
+fn softening_tames_close_passes() {
+    let spline = Softening::Spline { length: LENGTH };
+    let plummer = Softening::Plummer { length: LENGTH };
+    // Exactly Newtonian outside the kernel, but Plummer still isn't
+    assert_eq!(
+        pull(spline, 2.0 * LENGTH),
+        pull(Softening::None, 2.0 * LENGTH)
+    );
+    assert!(pull(plummer, 2.0 * LENGTH) < pull(Softening::None, 2.0 * LENGTH));
+    // Up close both go smoothly to nothing
+    for &softening in &[spline, plummer] {
+        let close = pull(softening, 1e-3 * LENGTH);
+        assert!(close < pull(softening, 0.5 * LENGTH));
+        assert!(close < 1e-3 * pull(Softening::None, 1e-3 * LENGTH));
+    }
+}
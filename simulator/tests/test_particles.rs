@@ -0,0 +1,126 @@
+mod common;
+
+use common::body;
+use euclid::default::{Point2D, Vector2D};
+use simulator::bodies::{Body, Kinemat, Orbiter};
+use simulator::config::SimulationConfig;
+use simulator::gravity::GravitySolver;
+use simulator::integrator::Leapfrog;
+use simulator::{CollisionKind, SolarSystem, GRAV_CONSTANT};
+
+const AU: f64 = 1.496e11;
+const SUN_MASS: f64 = 2e30;
+
+/// Going counterclockwise around the Sun in a circle, `dist` meters away at `angle` radians
+fn circling(dist: f64, angle: f64) -> Kinemat {
+    let (sin, cos) = angle.sin_cos();
+    let speed = (GRAV_CONSTANT * SUN_MASS / dist).sqrt();
+    Kinemat::new(
+        Point2D::new(cos * dist, sin * dist),
+        Vector2D::new(-sin * speed, cos * speed),
+    )
+}
+
+/// The Sun and Jupiter, plus `particles` heavy test particles in a ring
+fn system(particles: usize, solver: GravitySolver) -> SolarSystem {
+    let mut orbiters = vec![
+        Orbiter(body("Sun", SUN_MASS, 7e8), Kinemat::zero()),
+        Orbiter(body("Jupiter", 1.9e27, 7e7), circling(5.2 * AU, 0.0)),
+    ];
+    for n in 0..particles {
+        let angle = n as f64 / particles as f64 * std::f64::consts::PI * 2.0;
+        // Way too heavy to ignore if they actually pulled on things
+        orbiters.push(Orbiter(
+            Body {
+                test_particle: true,
+                ..body("Particle", 1e28, 1.0)
+            },
+            circling(2.7 * AU, angle),
+        ));
+    }
+    let mut solar_system =
+        SolarSystem::new(orbiters, SimulationConfig::default(), Box::new(Leapfrog));
+    solar_system.set_gravity_solver(solver);
+    solar_system
+}
+
+#[test]
+fn test_particles_dont_pull() {
+    for &solver in &[
+        GravitySolver::Direct,
+        GravitySolver::BarnesHut { theta: 0.5 },
+    ] {
+        let mut alone = system(0, solver);
+        let mut herding = system(500, solver);
+        for _ in 0..1000 {
+            alone.update(86_400.0);
+            herding.update(86_400.0);
+        }
+
+        // The Sun and Jupiter go exactly where they would without any particles
+        let alone = alone.get_orbiters();
+        let herding = herding.get_orbiters();
+        for (id, orbiter) in alone.iter() {
+            let kmat = herding[id].1;
+            assert_eq!(orbiter.1.pos, kmat.pos, "{} moved", orbiter.0.name);
+            assert_eq!(orbiter.1.vel, kmat.vel, "{} moved", orbiter.0.name);
+        }
+        // But the particles still feel the Sun, and Jupiter a little
+        for orbiter in herding.values().skip(2) {
+            let dist = orbiter.1.pos.to_vector().length();
+            assert!(
+                (dist - 2.7 * AU).abs() < 0.05 * AU,
+                "particle is {} m out",
+                dist
+            );
+        }
+    }
+}
+
+#[test]
+fn test_particles_get_deleted_instead_of_merging() {
+    let orbiters = vec![
+        Orbiter(body("Planet", 6e24, 6.4e6), Kinemat::zero()),
+        // Right on the planet's surface
+        Orbiter(
+            Body {
+                test_particle: true,
+                ..body("Particle", 1e30, 1e3)
+            },
+            Kinemat::new(Point2D::new(6.4e6, 0.0), Vector2D::zero()),
+        ),
+        // On top of each other, but they don't care
+        Orbiter(
+            Body {
+                test_particle: true,
+                ..body("Particle", 1e30, 1e3)
+            },
+            Kinemat::new(Point2D::new(1e9, 0.0), Vector2D::zero()),
+        ),
+        Orbiter(
+            Body {
+                test_particle: true,
+                ..body("Particle", 1e30, 1e3)
+            },
+            Kinemat::new(Point2D::new(1e9, 0.0), Vector2D::zero()),
+        ),
+    ];
+    let mut solar_system =
+        SolarSystem::new(orbiters, SimulationConfig::default(), Box::new(Leapfrog));
+    let ids: Vec<_> = solar_system.get_orbiters().keys().copied().collect();
+    solar_system.update(1.0);
+
+    let events: Vec<_> = solar_system.drain_collision_events().collect();
+    assert_eq!(events.len(), 1);
+    assert_eq!(events[0].kind, CollisionKind::Destroyed);
+    assert_eq!(events[0].results, vec![ids[0]]);
+
+    let orbiters = solar_system.get_orbiters();
+    assert_eq!(
+        orbiters.keys().copied().collect::<Vec<_>>(),
+        vec![ids[0], ids[2], ids[3]]
+    );
+    assert_eq!(orbiters[&ids[0]].0.mass, 6e24);
+    // Massless things don't add anything to the diagnostics either
+    assert_eq!(solar_system.momentum(), Vector2D::zero());
+}
@@ -1,11 +1,16 @@
 mod state;
 use state::State;
 
+use simulator::integrator::Leapfrog;
+
 use ggez::{
     conf::{Conf, WindowMode, WindowSetup},
     event, ContextBuilder,
 };
 
+/// Files ending in this are snapshots instead of scenarios
+pub const SNAPSHOT_EXTENSION: &str = ".orbsnap";
+
 pub fn main() {
     let c = Conf::new();
     let (ref mut ctx, ref mut event_loop) = ContextBuilder::new("orbit_simulator", "me")
@@ -29,11 +34,20 @@ pub fn main() {
             "systems/ours.json5".to_string()
         }
     };
-    let contents = std::fs::read_to_string(path_to_system).unwrap();
-    let bodies = loader::load(contents).unwrap();
-    println!("Loaded system data from file...");
-    let system = simulator::SolarSystem::new(bodies);
-    println!("Marshalled system data to solar system...");
+    let system = if path_to_system.ends_with(SNAPSHOT_EXTENSION) {
+        // Pick up a run where it left off
+        let system = simulator::SolarSystem::load_snapshot(&path_to_system).unwrap();
+        println!("Loaded snapshot from file...");
+        system
+    } else {
+        let contents = std::fs::read_to_string(path_to_system).unwrap();
+        let scenario = loader::load_scenario(contents).unwrap();
+        println!("Loaded system data from file...");
+        let system =
+            simulator::SolarSystem::new(scenario.orbiters, scenario.config, Box::new(Leapfrog));
+        println!("Marshalled system data to solar system...");
+        system
+    };
 
     let state = &mut State::new(ctx, system);
     println!("Start viewer!");
@@ -1,6 +1,9 @@
 //! Handles the state for the simulator.
 
-use simulator::{SimulationMode, SolarSystem};
+use simulator::{
+    bodies::Orbiter, gravity::GravitySolver, hierarchy::Hierarchy, integrator,
+    orbit::OrbitalElements, BodyId, Propagation, SolarSystem, Trajectory,
+};
 
 use euclid::default::{Point2D, Vector2D};
 use ggez::event::{EventHandler, KeyCode};
@@ -12,13 +15,22 @@ use ggez::{
 };
 
 use graphics::Color;
-use std::collections::HashSet;
+use std::collections::{BTreeMap, HashSet};
+use std::ops::Bound::{Excluded, Unbounded};
 
 /// The state of the solar system.
 pub struct State {
     solar_system: SolarSystem,
     /// How many seconds should be simulated per frame
     sim_seconds_per_frame: f64,
+    /// Whether to let the simulation pick its own step sizes
+    adaptive_steps: bool,
+    /// How many seconds into the simulation is being looked at, if the simulation is paused to scrub through the backups
+    previewing: Option<f64>,
+    /// What orbited what last time it was checked
+    hierarchy: Hierarchy,
+    /// How many frames until it's checked again
+    hierarchy_countdown: u32,
     /// All the keypresses last frame
     prev_keys: HashSet<KeyCode>,
 
@@ -30,20 +42,33 @@ pub struct State {
     /// Whether to fake the scale of planets by squishing them, for less existential dread
     fake_planet_scale: bool,
     /// If I'm focusing on a body
-    focused_body: Option<usize>,
+    focused_body: Option<BodyId>,
     /// The offset of that focus
     focus_offset: Point2D<f64>,
     /// If a pop-up appears on a planet, what's its id?
-    popuped_orbiter_id: Option<usize>,
+    popuped_orbiter_id: Option<BodyId>,
     /// Whether to even draw a popup
     draw_popup: bool,
+    /// Whether the popup lists everything the body was made from
+    show_ancestry: bool,
+    /// Whether to draw where the popuped body is going to go
+    show_prediction: bool,
+    /// Which body was last predicted, and where it's going.
+    /// Predicting is slow, so this only gets worked out again every so often
+    prediction: Option<(BodyId, Trajectory)>,
+    /// How many frames until the prediction gets worked out again
+    prediction_countdown: u32,
 }
 
 impl State {
     pub fn new(_ctx: &mut Context, solar_system: SolarSystem) -> Self {
-        let s = State {
+        State {
+            hierarchy: solar_system.get_hierarchy(),
+            hierarchy_countdown: HIERARCHY_CHECK_FRAMES,
             solar_system,
             sim_seconds_per_frame: SIM_SECONDS_PER_FRAME,
+            adaptive_steps: false,
+            previewing: None,
             prev_keys: HashSet::new(),
             distance_scale: DEFAULT_SCALE,
             planet_scale: DEFAULT_PLANET_SCALE,
@@ -52,8 +77,105 @@ impl State {
             focus_offset: Point2D::zero(),
             popuped_orbiter_id: None,
             draw_popup: true,
+            show_ancestry: false,
+            show_prediction: false,
+            prediction: None,
+            prediction_countdown: 0,
+        }
+    }
+
+    /// Everything that should be drawn: the moment being looked at, or the simulation if there isn't one.
+    fn visible_orbiters(&self) -> BTreeMap<BodyId, Orbiter> {
+        self.previewing
+            .and_then(|time| self.solar_system.state_at(time).ok())
+            .unwrap_or_else(|| self.solar_system.get_orbiters())
+    }
+
+    /// Every so often, print anything that's started orbiting something else.
+    fn check_hierarchy(&mut self) {
+        self.hierarchy_countdown = self.hierarchy_countdown.saturating_sub(1);
+        if self.hierarchy_countdown > 0 {
+            return;
+        }
+        self.hierarchy_countdown = HIERARCHY_CHECK_FRAMES;
+        let hierarchy = self.solar_system.get_hierarchy();
+        let name = |id: Option<BodyId>| match id.and_then(|id| self.solar_system.get_orbiter(id)) {
+            Some(orbiter) => orbiter.0.name,
+            None => "nothing".to_string(),
         };
-        s
+        for change in hierarchy.changes_since(&self.hierarchy) {
+            match change.new {
+                Some(_) => println!(
+                    "{} now orbits {} instead of {}",
+                    name(Some(change.id)),
+                    name(change.new),
+                    name(change.old)
+                ),
+                None => println!(
+                    "{} escaped from {}",
+                    name(Some(change.id)),
+                    name(change.old)
+                ),
+            }
+        }
+        self.hierarchy = hierarchy;
+    }
+
+    /// Print what orbits what, as a tree.
+    fn print_hierarchy(&self) {
+        let orbiters = self.solar_system.get_orbiters();
+        for (id, depth) in self.hierarchy.walk() {
+            if let Some(orbiter) = orbiters.get(&id) {
+                println!("{}{}", "  ".repeat(depth), orbiter.0.name);
+            }
+        }
+    }
+
+    /// Carry on simulating a different branch, and print how far things are from where they were in the old one.
+    fn switch_branch(&mut self, left: usize, next: usize) {
+        if let Err(err) = self.solar_system.switch_branch(next) {
+            println!("Couldn't switch branches: {}", err);
+            return;
+        }
+        self.hierarchy = self.solar_system.get_hierarchy();
+        let info = self.solar_system.list_branches()[next];
+        match info.parent {
+            Some(parent) => println!(
+                "Switched to branch {} (split off branch {} at step {})",
+                next, parent, info.fork_step
+            ),
+            None => println!("Switched to branch {}", next),
+        }
+        // Both branches exist, so this can't fail
+        let diff = self.solar_system.diff_branches(left, next).unwrap();
+        for body in diff.bodies.iter() {
+            println!(
+                "  {} is {:.3e}m from where it was",
+                body.name,
+                body.offset.length()
+            );
+        }
+        for (_, name) in diff.only_in_first.iter() {
+            println!("  {} doesn't exist here", name);
+        }
+        for (_, name) in diff.only_in_second.iter() {
+            println!("  {} only exists here", name);
+        }
+    }
+
+    /// How long the moment on screen is into the simulation, and what date that is if there's an epoch.
+    fn clock_text(&self) -> String {
+        let time = self
+            .previewing
+            .unwrap_or_else(|| self.solar_system.get_time());
+        let mut text = format!("Day {:.2}", time / SECONDS_PER_DAY);
+        if let Some(epoch) = self.solar_system.get_config().epoch {
+            text.push_str(&format!(", {}", epoch.after(time)));
+        }
+        if self.previewing.is_some() {
+            text.push_str(" (looking at backups)");
+        }
+        text
     }
 
     /// Fix the screen space to always have (0, 0) in the corner and (w, h) in the other.
@@ -68,11 +190,7 @@ impl EventHandler for State {
         const DESIRED_FPS: u32 = 60;
         while timer::check_update_time(ctx, DESIRED_FPS) {
             // Calculate how much of the simulation should be dt and how much should be steps per frame
-            // At small time scales, do a lot of small steps.
-            // At big time scales, do a few giant steps.
-            let steps_per_second = 10.0 * (self.sim_seconds_per_frame + 1_000.0).recip();
-            let steps_per_frame = self.sim_seconds_per_frame * steps_per_second;
-            let seconds_per_step = self.sim_seconds_per_frame / steps_per_frame;
+            let (steps_per_frame, seconds_per_step) = step_size(self.sim_seconds_per_frame);
             // Weird experiment...
             let seconds_per_step = if keyboard::is_key_pressed(ctx, KeyCode::Tab) {
                 -seconds_per_step
@@ -80,13 +198,39 @@ impl EventHandler for State {
                 seconds_per_step
             };
 
-            if let SimulationMode::Simulating = self.solar_system.get_mode() {
-                let frames = steps_per_frame.ceil() as u32;
-                for _ in 0..frames {
-                    self.solar_system.update(seconds_per_step);
+            if self.previewing.is_none() {
+                if self.adaptive_steps {
+                    self.solar_system.update_adaptive(
+                        self.sim_seconds_per_frame * seconds_per_step.signum(),
+                        ADAPTIVE_TOLERANCE,
+                    );
+                } else {
+                    let frames = steps_per_frame.ceil() as u32;
+                    for _ in 0..frames {
+                        self.solar_system.update(seconds_per_step);
+                    }
+                }
+            }
+            for event in self.solar_system.drain_collision_events() {
+                println!(
+                    "{:?} {} and {} at {:.0} m/s, releasing {:.3e} J (step {})",
+                    event.kind,
+                    event.bodies.0,
+                    event.bodies.1,
+                    event.impact_speed,
+                    event.energy_released,
+                    event.step
+                );
+                // Keep following whatever's left of the focused body
+                if let Some(id) = self.focused_body {
+                    let involved = id == event.bodies.0 || id == event.bodies.1;
+                    if involved && !event.results.contains(&id) {
+                        self.focused_body = event.results.first().copied();
+                    }
                 }
             }
-            let orbiters = self.solar_system.get_orbiters();
+            self.check_hierarchy();
+            let orbiters = self.visible_orbiters();
 
             // Press tilde to reset scales
             if keyboard::is_key_pressed(ctx, KeyCode::Grave) {
@@ -123,6 +267,104 @@ impl EventHandler for State {
                     self.draw_popup = !self.draw_popup;
                 }
 
+                // Print what orbits what with H
+                if keyboard::is_key_pressed(ctx, KeyCode::H)
+                    && !self.prev_keys.contains(&KeyCode::H)
+                {
+                    self.print_hierarchy();
+                }
+
+                // Toggle listing what the popuped body was made from with L
+                if keyboard::is_key_pressed(ctx, KeyCode::L)
+                    && !self.prev_keys.contains(&KeyCode::L)
+                {
+                    self.show_ancestry = !self.show_ancestry;
+                }
+
+                // Put the popuped body on rails around what it orbits, or take it off, with R
+                if keyboard::is_key_pressed(ctx, KeyCode::R)
+                    && !self.prev_keys.contains(&KeyCode::R)
+                {
+                    if let Some(id) = self.popuped_orbiter_id {
+                        let propagation = match self.solar_system.get_propagation(id) {
+                            Some(Propagation::Integrated) => self
+                                .hierarchy
+                                .primary(id)
+                                .map(|primary| Propagation::OnRails { primary }),
+                            _ => Some(Propagation::Integrated),
+                        };
+                        match propagation {
+                            Some(propagation) => {
+                                if let Err(err) = self.solar_system.set_propagation(id, propagation)
+                                {
+                                    println!("Couldn't put it on rails: {}", err);
+                                }
+                            }
+                            None => println!("It doesn't orbit anything to be on rails around"),
+                        }
+                    }
+                }
+
+                // Toggle drawing where the popuped body is going with P
+                if keyboard::is_key_pressed(ctx, KeyCode::P)
+                    && !self.prev_keys.contains(&KeyCode::P)
+                {
+                    self.show_prediction = !self.show_prediction;
+                    self.prediction = None;
+                }
+
+                // Write a snapshot with F5
+                if keyboard::is_key_pressed(ctx, KeyCode::F5)
+                    && !self.prev_keys.contains(&KeyCode::F5)
+                {
+                    let path = format!("snapshot{}", crate::SNAPSHOT_EXTENSION);
+                    match self.solar_system.save_snapshot(&path) {
+                        Ok(()) => println!("Saved snapshot to {}", path),
+                        Err(err) => println!("Couldn't save snapshot: {}", err),
+                    }
+                }
+
+                // Toggle adaptive step sizes with T
+                if keyboard::is_key_pressed(ctx, KeyCode::T)
+                    && !self.prev_keys.contains(&KeyCode::T)
+                {
+                    self.adaptive_steps = !self.adaptive_steps;
+                    println!("Adaptive steps: {}", self.adaptive_steps);
+                }
+
+                // Toggle the Barnes-Hut approximation with B
+                if keyboard::is_key_pressed(ctx, KeyCode::B)
+                    && !self.prev_keys.contains(&KeyCode::B)
+                {
+                    let solver = match self.solar_system.get_gravity_solver() {
+                        GravitySolver::Direct => GravitySolver::BarnesHut {
+                            theta: BARNES_HUT_THETA,
+                        },
+                        GravitySolver::BarnesHut { .. } => GravitySolver::Direct,
+                    };
+                    self.solar_system.set_gravity_solver(solver);
+                    println!("Switched gravity solver to {:?}", solver);
+                }
+
+                // Cycle through the integrators with I
+                if keyboard::is_key_pressed(ctx, KeyCode::I)
+                    && !self.prev_keys.contains(&KeyCode::I)
+                {
+                    let current = integrator::NAMES
+                        .iter()
+                        .position(|&name| name == self.solar_system.get_integrator_name())
+                        .unwrap_or(0);
+                    let next = integrator::NAMES[(current + 1) % integrator::NAMES.len()];
+                    // All the names in NAMES are valid
+                    self.solar_system
+                        .set_integrator(integrator::from_name(next).unwrap());
+                    println!(
+                        "Switched integrator to {} (energy drift so far: {:.3e})",
+                        next,
+                        self.solar_system.energy_drift()
+                    );
+                }
+
                 // BACKUPS & SPEED
                 // Speed and slow the simulation with []
                 if keyboard::is_key_pressed(ctx, KeyCode::LBracket)
@@ -134,31 +376,76 @@ impl EventHandler for State {
                 if keyboard::is_key_pressed(ctx, KeyCode::RBracket) {
                     self.sim_seconds_per_frame *= SPEED_SPEED;
                 }
-                match self.solar_system.get_mode() {
-                    SimulationMode::Simulating => {
-                        // Use Return to toggle modes
+                match self.previewing {
+                    None => {
+                        // Use Return to start looking through backups
                         if keyboard::is_key_pressed(ctx, KeyCode::Return)
                             && !self.prev_keys.contains(&KeyCode::Return)
                         {
-                            self.solar_system.enable_load();
+                            // Save right now so there's somewhere to come back to
+                            self.solar_system.save();
+                            let count = self.solar_system.list_saves().len();
+                            println!(
+                                "Backup size: {} using {}k bytes of ram",
+                                count,
+                                self.solar_system.history_bytes() / 1024
+                            );
+                            self.previewing = Some(self.solar_system.get_time());
+                        }
+
+                        // Use N to hop to the next branch, and see how it differs from the one we left
+                        if keyboard::is_key_pressed(ctx, KeyCode::N)
+                            && !self.prev_keys.contains(&KeyCode::N)
+                        {
+                            let left = self.solar_system.current_branch();
+                            let next = (left + 1) % self.solar_system.list_branches().len();
+                            if next != left {
+                                self.switch_branch(left, next);
+                            }
                         }
                     }
-                    SimulationMode::LoadingSave(_) => {
-                        // Change thing to load with ; and '
+                    Some(time) => {
+                        // Scrub through time with ; and ', as fast as the simulation would run
+                        let saves = self.solar_system.list_saves();
+                        let times = saves
+                            .iter()
+                            .map(|save| save.time)
+                            .chain(Some(self.solar_system.get_time()));
+                        let earliest = times.clone().fold(f64::INFINITY, f64::min);
+                        let latest = times.fold(f64::NEG_INFINITY, f64::max);
+                        let mut time = time;
                         if keyboard::is_key_pressed(ctx, KeyCode::Semicolon) {
-                            // Negative = older
-                            self.solar_system.change_load(-1);
+                            // Older
+                            time -= self.sim_seconds_per_frame;
                         }
                         if keyboard::is_key_pressed(ctx, KeyCode::Apostrophe) {
-                            // Positive = newer
-                            self.solar_system.change_load(1);
+                            // Newer
+                            time += self.sim_seconds_per_frame;
                         }
+                        self.previewing = Some(time.max(earliest).min(latest));
 
-                        // Use Return to toggle modes
+                        // Use Return to go back to the backup closest to then
                         if keyboard::is_key_pressed(ctx, KeyCode::Return)
                             && !self.prev_keys.contains(&KeyCode::Return)
                         {
-                            self.solar_system.exit_load();
+                            let closest = saves.iter().min_by(|a, b| {
+                                (a.time - time).abs().total_cmp(&(b.time - time).abs())
+                            });
+                            // There's always at least the backup made when scrubbing started
+                            let closest = closest.unwrap();
+                            match self.solar_system.restore_save(closest.index) {
+                                Ok(()) => {
+                                    println!("Went back to the backup at {:.0}s", closest.time);
+                                    // Don't report jumping back in time as things moving around
+                                    self.hierarchy = self.solar_system.get_hierarchy();
+                                }
+                                Err(err) => println!("Couldn't go back to that backup: {}", err),
+                            }
+                            self.previewing = None;
+                        }
+                        // Or Backspace to go back to where things were
+                        if keyboard::is_key_pressed(ctx, KeyCode::Back) {
+                            self.previewing = None;
                         }
                     }
                 };
@@ -178,6 +465,13 @@ impl EventHandler for State {
                     self.focus_offset.x += pan_speed;
                 }
 
+                // Stop focusing on things that don't exist anymore
+                if let Some(id) = self.focused_body {
+                    if !orbiters.contains_key(&id) {
+                        self.focused_body = None;
+                    }
+                }
+
                 if let Some(id) = self.focused_body {
                     // Press Space to exit focusing the planet
                     if keyboard::is_key_pressed(ctx, KeyCode::Space)
@@ -197,7 +491,7 @@ impl EventHandler for State {
                             && !self.prev_keys.contains(&KeyCode::Right)
                         {
                             self.focus_offset = Point2D::zero();
-                            let maybe_tup = orbiters.range(id + 1..).next();
+                            let maybe_tup = orbiters.range((Excluded(id), Unbounded)).next();
                             if let Some(tup) = maybe_tup {
                                 self.focused_body = Some(*tup.0); // Move it there!
                             } else {
@@ -237,10 +531,8 @@ impl EventHandler for State {
                     {
                         let id_maybe = if let Some(id) = self.popuped_orbiter_id {
                             Some(id)
-                        } else if let Some(id) = orbiters.keys().next() {
-                            Some(*id)
                         } else {
-                            None
+                            orbiters.keys().next().copied()
                         };
                         if let Some(first_valid_id) = id_maybe {
                             self.focused_body = Some(first_valid_id);
@@ -262,7 +554,7 @@ impl EventHandler for State {
     fn draw(&mut self, ctx: &mut Context) -> GameResult<()> {
         graphics::clear(ctx, Color::from_rgb_u32(0x200b2b));
 
-        let orbiters = self.solar_system.get_orbiters();
+        let orbiters = self.visible_orbiters();
         let focus_coord = self.focus_offset
             + match self.focused_body {
                 Some(id) => match orbiters.get(&id) {
@@ -278,7 +570,7 @@ impl EventHandler for State {
         let mut text_box_meshes = MeshBuilder::new();
 
         // id, (x, y), radius
-        let mut drawn_ids: Vec<(usize, (f32, f32), f32)> = Vec::new();
+        let mut drawn_ids: Vec<(BodyId, (f32, f32), f32)> = Vec::new();
         for (&id, orbiter) in orbiters.iter() {
             let relative_pos = orbiter.1.pos - focus_coord;
             // Make (0, 0) in pixel coords the center of the screen
@@ -320,7 +612,7 @@ impl EventHandler for State {
             }
         }
 
-        if drawn_ids.len() >= 1 {
+        if !drawn_ids.is_empty() {
             let draw = body_meshes.build(ctx)?;
             graphics::draw(ctx, &draw, DrawParam::default())?;
 
@@ -346,13 +638,136 @@ impl EventHandler for State {
                 })
             };
             self.popuped_orbiter_id = popuped_orbiter_id;
+
+            if let (true, None, Some(id)) =
+                (self.show_prediction, self.previewing, popuped_orbiter_id)
+            {
+                self.prediction_countdown = self.prediction_countdown.saturating_sub(1);
+                let stale = match &self.prediction {
+                    Some((predicted_id, _)) => {
+                        *predicted_id != id || self.prediction_countdown == 0
+                    }
+                    None => true,
+                };
+                if stale {
+                    // Step the way update does, ceil(steps_per_frame) steps of seconds_per_step every frame,
+                    // so it matches what actually happens. Adaptive steps change size as they go,
+                    // so with those turned on it's only close.
+                    let (steps_per_frame, seconds_per_step) = step_size(self.sim_seconds_per_frame);
+                    let steps = PREDICTION_FRAMES as f64 * steps_per_frame.ceil();
+                    let mut prediction = self.solar_system.predict(
+                        steps * seconds_per_step,
+                        seconds_per_step,
+                        PREDICTION_SAMPLE_EVERY,
+                        Some(&[id]),
+                    );
+                    self.prediction = prediction.remove(&id).map(|trajectory| (id, trajectory));
+                    self.prediction_countdown = PREDICTION_REFRESH_FRAMES;
+                }
+                if let Some((_, trajectory)) = &self.prediction {
+                    let points: Vec<Point2<f32>> = trajectory
+                        .samples
+                        .iter()
+                        .map(|(_, kmat)| {
+                            let relative_pos = kmat.pos - focus_coord;
+                            Point2::new(
+                                scr_w / 2f32 + (relative_pos.x / self.distance_scale) as f32,
+                                scr_h / 2f32 + (relative_pos.y / self.distance_scale) as f32,
+                            )
+                        })
+                        .collect();
+                    if points.len() >= 2 {
+                        let path = MeshBuilder::new()
+                            .line(&points, 1.0, Color::from_rgb_u32(0x88aaff))?
+                            .build(ctx)?;
+                        graphics::draw(ctx, &path, DrawParam::default())?;
+                    }
+                }
+            }
+
             if self.draw_popup {
                 if let Some(popuped_orbiter_id) = popuped_orbiter_id {
                     if let Some(popuped_orbiter) = orbiters.get(&popuped_orbiter_id) {
                         use graphics::{Text, TextFragment};
-                        let message = format!("\nBody info:\n- Mass: {:.2e} kg\n- Radius: {:.2e} m\nKinematic info:\n- Position: ({:.2e}, {:.2e}) m\n- Velocity: ({:.2e}, {:.2e}) m/s",
+                        let mut message = format!("\nBody info:\n- Mass: {:.2e} kg\n- Radius: {:.2e} m{}\nKinematic info:\n- Position: ({:.2e}, {:.2e}) m\n- Velocity: ({:.2e}, {:.2e}) m/s",
                             popuped_orbiter.0.mass, popuped_orbiter.0.radius,
+                            if popuped_orbiter.0.test_particle { "\n- Test particle, so its mass doesn't count" } else { "" },
                             popuped_orbiter.1.pos.x, popuped_orbiter.1.pos.y, popuped_orbiter.1.vel.x, popuped_orbiter.1.vel.y);
+                        // What orbits what hardly ever changes, so go by the hierarchy check_hierarchy keeps up to date,
+                        // even if what's on screen is a preview. The orbit itself comes from what's on screen.
+                        let primary = self
+                            .hierarchy
+                            .primary(popuped_orbiter_id)
+                            .and_then(|primary| orbiters.get(&primary));
+                        if let Some(primary_orbiter) = primary {
+                            let elements = OrbitalElements::from_kinemat(
+                                popuped_orbiter.1,
+                                primary_orbiter.1,
+                                self.solar_system.get_config().grav_constant
+                                    * (popuped_orbiter.0.gravitating_mass()
+                                        + primary_orbiter.0.gravitating_mass()),
+                            );
+                            message.push_str(&format!(
+                                "\nOrbit around {}:\n- Semi-major axis: {:.3e} m\n- Eccentricity: {:.4}\n- Periapsis: {:.3e} m",
+                                primary_orbiter.0.name,
+                                elements.semi_major_axis,
+                                elements.eccentricity,
+                                elements.periapsis()
+                            ));
+                            if let (Some(apoapsis), Some(period)) =
+                                (elements.apoapsis(), elements.period())
+                            {
+                                message.push_str(&format!(
+                                    "\n- Apoapsis: {:.3e} m\n- Period: {:.2} days",
+                                    apoapsis,
+                                    period / SECONDS_PER_DAY
+                                ));
+                            } else {
+                                message.push_str("\n- Escaping");
+                            }
+                            message.push_str(&format!(
+                                "\n- Argument of periapsis: {:.1}°\n- True anomaly: {:.1}°\n- Mean anomaly: {:.1}°{}",
+                                elements.argument_of_periapsis.to_degrees(),
+                                elements.true_anomaly.to_degrees(),
+                                elements.mean_anomaly.to_degrees(),
+                                if elements.clockwise { "\n- Clockwise" } else { "" }
+                            ));
+                        }
+                        if let Some(Propagation::OnRails { primary }) =
+                            self.solar_system.get_propagation(popuped_orbiter_id)
+                        {
+                            if let Some(primary_orbiter) = orbiters.get(&primary) {
+                                message.push_str(&format!(
+                                    "\nOn rails around {}",
+                                    primary_orbiter.0.name
+                                ));
+                            }
+                        }
+                        let ancestors = self.solar_system.get_ancestors(popuped_orbiter_id);
+                        if !ancestors.is_empty() {
+                            // Only count the things that weren't made from anything else.
+                            // Debris all shares the same ancestors, so they can show up more than once.
+                            let originals = ancestors
+                                .iter()
+                                .filter(|a| !a.has_ancestors)
+                                .map(|a| a.id)
+                                .collect::<HashSet<_>>()
+                                .len();
+                            message.push_str(&format!("\nFormed from {} bodies", originals));
+                            if self.show_ancestry {
+                                for ancestor in ancestors.iter() {
+                                    message.push_str(&format!(
+                                        "\n{}- {}: {:.2e} kg, merged at {:.2e} s",
+                                        "  ".repeat(ancestor.generation),
+                                        ancestor.name,
+                                        ancestor.mass,
+                                        ancestor.merged_time
+                                    ));
+                                }
+                            } else {
+                                message.push_str(" (L to expand)");
+                            }
+                        }
                         let body_text = Text::new(TextFragment::new(message));
                         let (text_w, text_h) = body_text.dimensions(ctx);
                         let (text_w, text_h) = (text_w as f32, text_h as f32);
@@ -428,6 +843,16 @@ impl EventHandler for State {
             }
         }
 
+        // The clock goes in the corner
+        let clock = graphics::Text::new(graphics::TextFragment::new(self.clock_text()));
+        graphics::queue_text(ctx, &clock, Point2::new(10.0, 10.0), Some(graphics::WHITE));
+        graphics::draw_queued_text(
+            ctx,
+            DrawParam::default(),
+            None,
+            graphics::default_filter(ctx),
+        )?;
+
         graphics::present(ctx)
     }
 
@@ -436,6 +861,14 @@ impl EventHandler for State {
     }
 }
 
+/// How many steps to take each frame and how long each one is, when simulating `sim_seconds_per_frame` every frame.
+/// At small time scales, do a lot of small steps. At big time scales, do a few giant steps.
+fn step_size(sim_seconds_per_frame: f64) -> (f64, f64) {
+    let steps_per_second = 10.0 * (sim_seconds_per_frame + 1_000.0).recip();
+    let steps_per_frame = sim_seconds_per_frame * steps_per_second;
+    (steps_per_frame, sim_seconds_per_frame / steps_per_frame)
+}
+
 fn scale_planet(radius: f64, scale: f64, fake: bool) -> f32 {
     if fake {
         (10f64 * (radius / scale).powf(0.3)) as f32
@@ -445,6 +878,8 @@ fn scale_planet(radius: f64, scale: f64, fake: bool) -> f32 {
     .max(0.5f32) // Everything has to be at least half a pixel wide, unfortunately. Otherwise it becomes impossible to see.
 }
 
+/// Check for things being captured or escaping once every this many frames
+const HIERARCHY_CHECK_FRAMES: u32 = 30;
 const PAN_SPEED: f64 = 10f64; // Pan this many pixels per frame
 const ZOOM_SPEED: f64 = 1.1f64; // multiply / divide by this many meters per frame
 const SPEED_SPEED: f64 = 1.05f64; // speed speed... the number of seconds simulated per frame changes by this amount per frame
@@ -456,4 +891,17 @@ const PROPORTION_REQUIRED_FOR_LABEL: f32 = 0.000005;
 const DEFAULT_SCALE: f64 = 1e10;
 const DEFAULT_PLANET_SCALE: f64 = 1f64;
 
-const SIM_SECONDS_PER_FRAME: f64 = 60f64 * 60f64 * 24f64; // Each frame is 24 * 60 * 60 seconds, or one day
+/// How big a fraction of the shortest orbital timescale each adaptive step can be
+const ADAPTIVE_TOLERANCE: f64 = 0.01;
+
+/// Opening angle to use when Barnes-Hut is turned on
+const BARNES_HUT_THETA: f64 = 0.5;
+
+/// How many frames ahead to predict where the popuped body is going
+const PREDICTION_FRAMES: u32 = 300;
+/// Draw a point of the prediction every this many steps
+const PREDICTION_SAMPLE_EVERY: usize = 4;
+/// Work out the prediction again once every this many frames
+const PREDICTION_REFRESH_FRAMES: u32 = 30;
+const SECONDS_PER_DAY: f64 = 60f64 * 60f64 * 24f64;
+const SIM_SECONDS_PER_FRAME: f64 = SECONDS_PER_DAY; // Each frame is 24 * 60 * 60 seconds, or one day